@@ -0,0 +1,153 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::collector::{self, NetworkCollector, ProcessCollector};
+use crate::config::Config;
+use crate::export::{self, PROCESS_CSV_HEADER};
+use crate::units;
+use crate::{format_uptime, sort_processes, SharedState, TaskCounts};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BatchFormat {
+    Text,
+    Csv,
+    Ndjson,
+}
+
+impl FromStr for BatchFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(BatchFormat::Text),
+            "csv" => Ok(BatchFormat::Csv),
+            "ndjson" | "jsonl" => Ok(BatchFormat::Ndjson),
+            _ => Err("expected text, csv or ndjson".to_string()),
+        }
+    }
+}
+
+/// `--batch`: runs the collectors without a terminal UI and prints every sample to
+/// stdout. Runs forever unless `iterations` is set, like `top -b`.
+pub fn run(iterations: Option<usize>, config: &Config, format: BatchFormat) -> io::Result<()> {
+    let mut process_collector = ProcessCollector::new();
+    // No screen to keep responsive, every sample gets all of them
+    if config.wants_smaps() {
+        process_collector.set_pss_budget(Some(Duration::MAX));
+    }
+    let mut network_collector = NetworkCollector::new(config.interfaces.clone());
+    let mut state = SharedState::new(config.interval());
+    let mut out = io::stdout().lock();
+
+    if format == BatchFormat::Csv {
+        writeln!(out, "timestamp,{}", PROCESS_CSV_HEADER)?;
+    }
+
+    let mut done = 0;
+    while iterations.is_none_or(|n| done < n) {
+        thread::sleep(config.interval());
+        process_collector.collect().apply(&mut state);
+        collector::apply_network(network_collector.collect(false), &mut state);
+        sort_processes(&mut state.processes, config.sort, config.sort.default_descending());
+
+        match format {
+            BatchFormat::Text => write_text(&mut out, &state)?,
+            BatchFormat::Csv => {
+                let timestamp = export::format_timestamp(SystemTime::now());
+                for p in &state.processes {
+                    let mut fields = vec![timestamp.clone()];
+                    fields.extend(export::process_csv_fields(p));
+                    export::write_csv_row(&mut out, &fields)?;
+                }
+            }
+            BatchFormat::Ndjson => writeln!(out, "{}", export::snapshot_json_line(&state)?)?,
+        }
+        out.flush()?;
+        done += 1;
+    }
+    Ok(())
+}
+
+fn write_text(out: &mut impl Write, state: &SharedState) -> io::Result<()> {
+    let host = &state.host;
+    let tasks = TaskCounts::from_processes(&state.processes);
+    writeln!(
+        out,
+        "{} {}  up {}  load average: {:.2}, {:.2}, {:.2}",
+        host.hostname,
+        host.kernel,
+        format_uptime(host.uptime),
+        host.load_average[0],
+        host.load_average[1],
+        host.load_average[2]
+    )?;
+    writeln!(
+        out,
+        "Tasks: {} total, {} running, {} sleeping, {} stopped, {} zombie",
+        tasks.total, tasks.running, tasks.sleeping, tasks.stopped, tasks.zombie
+    )?;
+    writeln!(
+        out,
+        "{}  CPU: {:.1}%  RAM: {} / {}  Swap: {} / {}  Disk: ↓{} ↑{}",
+        export::format_timestamp(SystemTime::now()),
+        state.total_cpu_usage,
+        units::bytes(state.used_memory),
+        units::bytes(state.total_memory),
+        units::bytes(state.used_swap),
+        units::bytes(state.total_swap),
+        units::rate(state.disk_read_bps),
+        units::rate(state.disk_write_bps),
+    )?;
+    for disk in state.disks.iter().filter(|d| d.parent.is_none()) {
+        writeln!(
+            out,
+            "Disk {:<11} R {:>12} W {:>12} r/s {:>7.1} w/s {:>7.1} await {:>6.2}/{:<6.2} aqu {:>5.2} util {:>5.1}%",
+            disk.name,
+            units::rate(disk.read_bps),
+            units::rate(disk.write_bps),
+            disk.reads_per_sec,
+            disk.writes_per_sec,
+            disk.read_await,
+            disk.write_await,
+            disk.queue_depth,
+            disk.util
+        )?;
+    }
+    for net in &state.network_data {
+        writeln!(
+            out,
+            "Net {:<12} RX {:>12} TX {:>12}",
+            net.name,
+            units::net_rate(net.rx_bps),
+            units::net_rate(net.tx_bps)
+        )?;
+    }
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{:>8} {:<12} {:>7} {:>11} {:>7} {:>11} {:<9} {:>9}  NAME",
+        "PID", "USER", "CPU%", "MEM", "THREADS", "DISK/s", "STATUS", "RUNTIME"
+    )?;
+    for p in &state.processes {
+        writeln!(
+            out,
+            "{:>8} {:<12} {:>7.1} {:>11} {:>7} {:>11} {:<9} {:>9}  {}",
+            p.pid.to_string(),
+            truncate(&p.user, 12),
+            p.cpu_usage,
+            units::bytes(p.memory),
+            p.threads,
+            units::rate(p.disk_read_bps + p.disk_write_bps),
+            truncate(&p.status, 9),
+            p.run_time,
+            p.name
+        )?;
+    }
+    writeln!(out)
+}
+
+fn truncate(s: &str, width: usize) -> String {
+    s.chars().take(width).collect()
+}
@@ -0,0 +1,203 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::batch::BatchFormat;
+use crate::config::{Columns, Panels, Pattern};
+use crate::units::UnitSystem;
+use crate::SortBy;
+
+pub const USAGE: &str = "\
+Usage: TaskManager_lite_test [OPTIONS]
+
+Options:
+  --snapshot <PATH>   Write one sample of every process and the system totals to
+                      PATH (.csv or .json) and exit
+  --batch             Print samples to stdout instead of starting the UI
+  --iterations <N>    Stop --batch after N samples [default: run until killed]
+  --format <FORMAT>   --batch output: text, csv or ndjson [default: text]
+  --record <PATH>     Append every collected sample to PATH while the UI runs
+  --replay <PATH>     Play back a recording made with --record instead of
+                      showing live data
+  --metrics-addr <ADDR>
+                      Serve Prometheus/OpenMetrics metrics on ADDR/metrics,
+                      e.g. 127.0.0.1:9184 (needs the `metrics` feature, UI
+                      only)
+  --metrics-top <N>   Number of processes exported per metric [default: 10]
+  --config <PATH>     Read settings from PATH instead of
+                      ~/.config/taskmanager-lite/config.toml
+
+Settings (override the config file):
+  --interval <SECS>   Seconds between samples [default: 1]
+  --ui-interval <SECS>
+                      Seconds between redraws [default: 0.1]
+  --detail-interval <SECS>
+                      Seconds between refreshes of the detail pane [default: 2]
+  --max-processes <N> Rows in the process table, 0 for all [default: 0]
+  --max-interfaces <N>
+                      Rows in the network table [default: 6]
+  --sort <COLUMN>     Initial sort: name, pid, user, cpu, memory, threads, disk,
+                      status, runtime or any other --columns name [default: cpu]
+  --per-core          Start with the per-core CPU grid shown ('1' toggles it)
+  --columns <LIST>    Comma-separated process table columns: name, pid, user, cpu,
+                      memory, threads, disk, status, runtime, nice, ioprio,
+                      ppid, start, cmd, fds, pss, uss [default: name through
+                      runtime]
+  --panels <LIST>     Comma-separated panels to show: system, processes, memory,
+                      network, disks, command [default: all]
+  --threshold <NAME=PERCENT>
+                      Color threshold, e.g. cpu_warn=60 (repeatable). Names:
+                      cpu_warn, cpu_crit, process_memory, ram_warn, ram_crit,
+                      swap_warn, swap_crit, fd_warn, fd_crit, disk_warn,
+                      disk_crit
+  --iface-include <PATTERN>
+                      Only list interfaces matching PATTERN, a glob like eth*
+                      or a regex like re:^(en|wl) (repeatable)
+  --iface-exclude <PATTERN>
+                      Hide interfaces matching PATTERN (repeatable, replaces the
+                      default lo, *npcap*, *nocap* list)
+  --units <SYSTEM>    Sizes in iec (KiB, 1024), si (kB, 1000) or raw (plain
+                      numbers, for scripts) [default: iec]
+  --precision <N>     Decimals of scaled sizes and rates [default: 1]
+  --bits              Show network rates in bits per second
+  -h, --help          Show this help";
+
+#[derive(Debug)]
+pub struct Args {
+    pub snapshot: Option<PathBuf>,
+    pub batch: bool,
+    pub iterations: Option<usize>,
+    pub format: Option<BatchFormat>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub metrics_addr: Option<SocketAddr>,
+    pub metrics_top: usize,
+    pub help: bool,
+    pub config: Option<PathBuf>,
+    pub interval: Option<f64>,
+    pub ui_interval: Option<f64>,
+    pub detail_interval: Option<f64>,
+    pub max_processes: Option<usize>,
+    pub max_interfaces: Option<usize>,
+    pub sort: Option<SortBy>,
+    pub per_core: bool,
+    pub columns: Option<Columns>,
+    pub panels: Option<Panels>,
+    pub thresholds: Vec<(String, f64)>,
+    pub iface_include: Vec<Pattern>,
+    pub iface_exclude: Vec<Pattern>,
+    pub units: Option<UnitSystem>,
+    pub precision: Option<usize>,
+    pub bits: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            snapshot: None,
+            batch: false,
+            iterations: None,
+            format: None,
+            record: None,
+            replay: None,
+            metrics_addr: None,
+            metrics_top: 10,
+            help: false,
+            config: None,
+            interval: None,
+            ui_interval: None,
+            detail_interval: None,
+            max_processes: None,
+            max_interfaces: None,
+            sort: None,
+            per_core: false,
+            columns: None,
+            panels: None,
+            thresholds: Vec::new(),
+            iface_include: Vec::new(),
+            iface_exclude: Vec::new(),
+            units: None,
+            precision: None,
+            bits: false,
+        }
+    }
+}
+
+impl Args {
+    pub fn parse() -> Result<Args, String> {
+        Args::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--snapshot" => parsed.snapshot = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--batch" => parsed.batch = true,
+                "--iterations" => parsed.iterations = Some(parse_value(&arg, args.next())?),
+                "--format" => parsed.format = Some(parse_value(&arg, args.next())?),
+                "--record" => parsed.record = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--replay" => parsed.replay = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--metrics-addr" => parsed.metrics_addr = Some(parse_value(&arg, args.next())?),
+                "--metrics-top" => parsed.metrics_top = parse_value(&arg, args.next())?,
+                "-h" | "--help" => parsed.help = true,
+                "--config" => parsed.config = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--interval" => parsed.interval = Some(parse_value(&arg, args.next())?),
+                "--ui-interval" => parsed.ui_interval = Some(parse_value(&arg, args.next())?),
+                "--detail-interval" => parsed.detail_interval = Some(parse_value(&arg, args.next())?),
+                "--max-processes" => parsed.max_processes = Some(parse_value(&arg, args.next())?),
+                "--max-interfaces" => parsed.max_interfaces = Some(parse_value(&arg, args.next())?),
+                "--sort" => parsed.sort = Some(parse_value(&arg, args.next())?),
+                "--per-core" => parsed.per_core = true,
+                "--columns" => parsed.columns = Some(parse_value(&arg, args.next())?),
+                "--panels" => parsed.panels = Some(parse_value(&arg, args.next())?),
+                "--threshold" => {
+                    let raw = value(&arg, args.next())?;
+                    let (name, percent) = raw
+                        .split_once('=')
+                        .ok_or_else(|| format!("--threshold expects NAME=PERCENT, got '{}'", raw))?;
+                    let percent = percent
+                        .trim()
+                        .parse()
+                        .map_err(|e| format!("invalid value '{}' for --threshold {}: {}", percent, name, e))?;
+                    parsed.thresholds.push((name.trim().to_string(), percent));
+                }
+                "--iface-include" => parsed.iface_include.push(parse_value(&arg, args.next())?),
+                "--iface-exclude" => parsed.iface_exclude.push(parse_value(&arg, args.next())?),
+                "--units" => parsed.units = Some(parse_value(&arg, args.next())?),
+                "--precision" => parsed.precision = Some(parse_value(&arg, args.next())?),
+                "--bits" => parsed.bits = true,
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+        if parsed.record.is_some() && parsed.replay.is_some() {
+            return Err("--record and --replay can't be used together".to_string());
+        }
+        if parsed.snapshot.is_some() && (parsed.record.is_some() || parsed.replay.is_some()) {
+            return Err("--snapshot can't be combined with --record or --replay".to_string());
+        }
+        if parsed.batch && (parsed.snapshot.is_some() || parsed.record.is_some() || parsed.replay.is_some()) {
+            return Err("--batch can't be combined with --snapshot, --record or --replay".to_string());
+        }
+        if !parsed.batch && (parsed.iterations.is_some() || parsed.format.is_some()) {
+            return Err("--iterations and --format only apply to --batch".to_string());
+        }
+        if parsed.metrics_addr.is_some() && (parsed.batch || parsed.snapshot.is_some()) {
+            return Err("--metrics-addr only works with the UI, not with --batch or --snapshot".to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+fn value(flag: &str, next: Option<String>) -> Result<String, String> {
+    next.ok_or_else(|| format!("{} needs a value", flag))
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, next: Option<String>) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    let raw = value(flag, next)?;
+    raw.parse()
+        .map_err(|e| format!("invalid value '{}' for {}: {}", raw, flag, e))
+}
@@ -0,0 +1,439 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sysinfo::{Networks, Pid, System, ThreadKind, Users};
+
+use crate::config::InterfaceFilter;
+use crate::diskstats::{self, DiskCollector, DiskInfo};
+use crate::history::ProcessHistory;
+use crate::meminfo::{self, MemInfo};
+use crate::procfs::{self, MemoryUsage};
+use crate::proc_control;
+use crate::{CoreInfo, HostInfo, NetworkInfo, ProcessInfo, SharedState};
+
+/// Everything the process monitor produces in one tick.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProcessSample {
+    pub processes: Vec<ProcessInfo>,
+    #[serde(default)]
+    pub host: HostInfo,
+    pub cpu_model: String,
+    pub total_cpu_usage: f32,
+    /// Missing from recordings made before per-core stats were collected
+    #[serde(default)]
+    pub cores: Vec<CoreInfo>,
+    pub total_memory: u64,
+    pub used_memory: u64,
+    pub available_memory: u64,
+    pub total_swap: u64,
+    pub used_swap: u64,
+    pub disk_read_bps: f64,
+    pub disk_write_bps: f64,
+    /// None off Linux and in recordings made before it was collected
+    #[serde(default)]
+    pub meminfo: Option<MemInfo>,
+    /// Per block device, empty in recordings made before it was collected
+    #[serde(default)]
+    pub disks: Vec<DiskInfo>,
+}
+
+impl ProcessSample {
+    pub fn apply(self, state: &mut SharedState) {
+        let history = &mut state.history;
+        history.cpu.push(self.total_cpu_usage as f64);
+        history.memory.push(percent(self.used_memory, self.total_memory));
+        history.swap.push(percent(self.used_swap, self.total_swap));
+        history.disk_read.push(self.disk_read_bps);
+        history.disk_write.push(self.disk_write_bps);
+
+        // Per-process history, dropping PIDs that went away. A reused PID starts over.
+        // Thread entries are skipped, only processes get a history.
+        let procs = self.processes.iter().filter(|p| !p.is_thread);
+        let alive: HashSet<Pid> = procs.clone().map(|p| p.pid).collect();
+        state.process_history.retain(|pid, _| alive.contains(pid));
+        let interval = state.history.interval();
+        for p in procs {
+            let entry = state
+                .process_history
+                .entry(p.pid)
+                .or_insert_with(|| ProcessHistory::new(&p.name, interval));
+            if entry.name != p.name {
+                *entry = ProcessHistory::new(&p.name, interval);
+            }
+            entry.push(p.cpu_usage, p.memory);
+        }
+
+        state.processes = self.processes;
+        state.host = self.host;
+        state.cpu_model = self.cpu_model;
+        state.total_cpu_usage = self.total_cpu_usage;
+        state.cores = self.cores;
+        state.total_memory = self.total_memory;
+        state.used_memory = self.used_memory;
+        state.available_memory = self.available_memory;
+        state.total_swap = self.total_swap;
+        state.used_swap = self.used_swap;
+        state.disk_read_bps = self.disk_read_bps;
+        state.disk_write_bps = self.disk_write_bps;
+        state.meminfo = self.meminfo;
+        state.disks = self.disks;
+    }
+}
+
+/// Processes, CPU, memory and disk I/O. Rates are computed against the previous `collect`.
+pub struct ProcessCollector {
+    sys: System,
+    users: Users,
+    cpu_model: String,
+    hostname: String,
+    kernel: String,
+    last_proc_read_total: u64,
+    last_proc_write_total: u64,
+    last_tick: Instant,
+    /// None unless PSS/USS are wanted, see `set_pss_budget`
+    pss_budget: Option<Duration>,
+    pss: HashMap<Pid, PssEntry>,
+    /// Read nice and I/O priority, see `set_priorities`
+    priorities: bool,
+    /// Count open descriptors, see `set_fd_counts`
+    fd_counts: bool,
+    disks: DiskCollector,
+}
+
+/// How long each sample may spend reading smaps_rollup when the UI asks for PSS/USS.
+/// The kernel walks the page tables of the process for it, which takes milliseconds for a
+/// big one, so the rest waits for the next sample instead of stalling this one.
+pub const PSS_BUDGET: Duration = Duration::from_millis(100);
+
+/// PSS/USS older than this are read again
+const PSS_MAX_AGE: Duration = Duration::from_secs(5);
+
+struct PssEntry {
+    /// Tells a reused PID apart
+    start_time: u64,
+    /// None when smaps_rollup isn't readable, not retried until it's stale
+    usage: Option<MemoryUsage>,
+    read_at: Instant,
+}
+
+impl ProcessCollector {
+    pub fn new() -> Self {
+        let mut sys = System::new_all();
+        sys.refresh_all();
+        let cpu_model = sys
+            .cpus()
+            .first()
+            .map_or("Unknown".to_string(), |cpu| cpu.brand().to_string());
+        let (last_proc_read_total, last_proc_write_total) = disk_totals(&sys);
+
+        ProcessCollector {
+            sys,
+            users: Users::new_with_refreshed_list(),
+            cpu_model,
+            hostname: System::host_name().unwrap_or_else(|| "unknown".to_string()),
+            kernel: System::kernel_version().unwrap_or_default(),
+            last_proc_read_total,
+            last_proc_write_total,
+            last_tick: Instant::now(),
+            pss_budget: None,
+            pss: HashMap::new(),
+            priorities: true,
+            fd_counts: true,
+            disks: DiskCollector::new(),
+        }
+    }
+
+    /// Whether to fill in nice and I/O priority, a /proc read and a syscall per process and
+    /// thread. On by default, the UI turns it off while neither column is shown or sorted on.
+    pub fn set_priorities(&mut self, priorities: bool) {
+        self.priorities = priorities;
+    }
+
+    /// Whether to count open descriptors, a listing of /proc/<pid>/fd per process. On by
+    /// default like `set_priorities`.
+    pub fn set_fd_counts(&mut self, fd_counts: bool) {
+        self.fd_counts = fd_counts;
+    }
+
+    /// Fill in PSS/USS, spending at most `budget` per `collect` on it. None stops reading
+    /// them and forgets the cached values.
+    pub fn set_pss_budget(&mut self, budget: Option<Duration>) {
+        self.pss_budget = budget;
+        if budget.is_none() {
+            self.pss.clear();
+        }
+    }
+
+    pub fn collect(&mut self) -> ProcessSample {
+        let sys = &mut self.sys;
+        sys.refresh_all();
+        self.users.refresh_list();
+
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_tick).as_secs_f64().max(1e-9);
+        let (proc_read_total, proc_write_total) = disk_totals(sys);
+
+        // The devices see kernel writeback and swap too, the per-process sums are the fallback
+        let disks = self.disks.collect();
+        let (disk_read_bps, disk_write_bps) = diskstats::physical_totals(&disks).unwrap_or_else(|| {
+            (
+                (proc_read_total.saturating_sub(self.last_proc_read_total)) as f64 / dt,
+                (proc_write_total.saturating_sub(self.last_proc_write_total)) as f64 / dt,
+            )
+        });
+
+        self.last_proc_read_total = proc_read_total;
+        self.last_proc_write_total = proc_write_total;
+        self.last_tick = now;
+
+        let users = &self.users;
+        let mut processes: Vec<ProcessInfo> = sys
+            .processes()
+            .values()
+            .map(|p| ProcessInfo {
+                name: p.name().to_string(),
+                pid: p.pid(),
+                cpu_usage: p.cpu_usage(),
+                memory: p.memory(),
+                status: format!("{:?}", p.status()),
+                run_time: p.run_time(),
+                parent: p.parent(),
+                is_thread: p.thread_kind() == Some(ThreadKind::Userland),
+                cmd: p.cmd().join(" "),
+                user: p
+                    .user_id()
+                    .and_then(|uid| users.get_user_by_id(uid))
+                    .map_or(String::new(), |u| u.name().to_string()),
+                threads: p.tasks().map_or(1, |tasks| tasks.len().max(1)),
+                // read_bytes/written_bytes are the amounts since the previous refresh
+                disk_read_bps: p.disk_usage().read_bytes as f64 / dt,
+                disk_write_bps: p.disk_usage().written_bytes as f64 / dt,
+                nice: self.priorities.then(|| proc_control::nice_of(p.pid())).flatten(),
+                io_priority: self
+                    .priorities
+                    .then(|| proc_control::io_priority_of(p.pid()))
+                    .flatten()
+                    .map(|(class, level)| proc_control::format_io_priority(class, level)),
+                start_time: p.start_time(),
+                fd_count: None,
+                pss: None,
+                uss: None,
+            })
+            .collect();
+        if self.fd_counts {
+            fill_fd_counts(&mut processes);
+        }
+        if let Some(budget) = self.pss_budget {
+            update_pss(&mut self.pss, &mut processes, budget);
+        }
+
+        let load = System::load_average();
+        ProcessSample {
+            processes,
+            host: HostInfo {
+                hostname: self.hostname.clone(),
+                kernel: self.kernel.clone(),
+                uptime: System::uptime(),
+                boot_time: System::boot_time(),
+                load_average: [load.one, load.five, load.fifteen],
+            },
+            cpu_model: self.cpu_model.clone(),
+            total_cpu_usage: sys.global_cpu_info().cpu_usage(),
+            cores: sys
+                .cpus()
+                .iter()
+                .map(|cpu| CoreInfo {
+                    usage: cpu.cpu_usage(),
+                    frequency: cpu.frequency(),
+                })
+                .collect(),
+            total_memory: sys.total_memory(),
+            used_memory: sys.used_memory(),
+            available_memory: sys.available_memory(),
+            total_swap: sys.total_swap(),
+            used_swap: sys.used_swap(),
+            disk_read_bps,
+            disk_write_bps,
+            meminfo: meminfo::read(),
+            disks,
+        }
+    }
+}
+
+/// Counts the descriptors of each process once. Threads share the table of their process
+/// and get its count.
+fn fill_fd_counts(processes: &mut [ProcessInfo]) {
+    let counts: HashMap<Pid, usize> = processes
+        .iter()
+        .filter(|p| !p.is_thread)
+        .filter_map(|p| Some((p.pid, procfs::fd_count(p.pid)?)))
+        .collect();
+    for p in processes.iter_mut() {
+        let owner = if p.is_thread { p.parent } else { Some(p.pid) };
+        p.fd_count = owner.and_then(|pid| counts.get(&pid).copied());
+    }
+}
+
+fn percent(used: u64, total: u64) -> f64 {
+    if total > 0 {
+        used as f64 / total as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Stores a network sample and adds the summed rates to the history. Bridge ports are left
+/// out of the sums when their bridge is listed, their traffic already shows up there.
+pub fn apply_network(rows: Vec<NetworkInfo>, state: &mut SharedState) {
+    let top_level: Vec<&NetworkInfo> = rows.iter().filter(|n| !is_grouped_port(n, &rows)).collect();
+    state.history.net_rx.push(top_level.iter().map(|n| n.rx_bps).sum());
+    state.history.net_tx.push(top_level.iter().map(|n| n.tx_bps).sum());
+    state.network_data = rows;
+}
+
+/// True for an interface whose bridge is also in `rows`.
+pub fn is_grouped_port(net: &NetworkInfo, rows: &[NetworkInfo]) -> bool {
+    net.master
+        .as_ref()
+        .is_some_and(|master| rows.iter().any(|n| &n.name == master))
+}
+
+/// Re-reads the stalest PSS/USS values, the never-read ones first, until `budget` is spent,
+/// then copies the cached values into `processes`.
+fn update_pss(cache: &mut HashMap<Pid, PssEntry>, processes: &mut [ProcessInfo], budget: Duration) {
+    let started = Instant::now();
+    let alive: HashSet<Pid> = processes.iter().map(|p| p.pid).collect();
+    cache.retain(|pid, _| alive.contains(pid));
+
+    // Threads share the memory of their process, reading them again would only cost time
+    let mut stale: Vec<(Option<Instant>, Pid, u64)> = processes
+        .iter()
+        .filter(|p| !p.is_thread)
+        .filter_map(|p| match cache.get(&p.pid) {
+            Some(e) if e.start_time == p.start_time && e.read_at.elapsed() < PSS_MAX_AGE => None,
+            Some(e) if e.start_time == p.start_time => Some((Some(e.read_at), p.pid, p.start_time)),
+            _ => Some((None, p.pid, p.start_time)),
+        })
+        .collect();
+    stale.sort_by_key(|(read_at, ..)| *read_at);
+    for (_, pid, start_time) in stale {
+        if started.elapsed() >= budget {
+            break;
+        }
+        let usage = procfs::memory_usage(pid).ok();
+        cache.insert(pid, PssEntry { start_time, usage, read_at: Instant::now() });
+    }
+
+    for p in processes.iter_mut() {
+        if let Some(usage) = cache.get(&p.pid).filter(|e| e.start_time == p.start_time).and_then(|e| e.usage) {
+            p.pss = Some(usage.pss);
+            p.uss = Some(usage.uss());
+        }
+    }
+}
+
+fn disk_totals(sys: &System) -> (u64, u64) {
+    sys.processes().values().fold((0, 0), |(read, write), p| {
+        let usage = p.disk_usage();
+        (read + usage.total_read_bytes, write + usage.total_written_bytes)
+    })
+}
+
+/// Per-interface traffic totals and rates.
+pub struct NetworkCollector {
+    networks: Networks,
+    filter: InterfaceFilter,
+    last_net_totals: HashMap<String, (u64, u64)>,
+    last_tick: Instant,
+}
+
+impl NetworkCollector {
+    pub fn new(filter: InterfaceFilter) -> Self {
+        let networks = Networks::new_with_refreshed_list();
+        let last_net_totals = networks
+            .iter()
+            .map(|(name, data)| (name.to_string(), (data.total_received(), data.total_transmitted())))
+            .collect();
+        NetworkCollector {
+            networks,
+            filter,
+            last_net_totals,
+            last_tick: Instant::now(),
+        }
+    }
+
+    /// With `show_all` the include/exclude patterns are ignored.
+    pub fn collect(&mut self, show_all: bool) -> Vec<NetworkInfo> {
+        self.networks.refresh();
+
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_tick).as_secs_f64().max(1e-9);
+        self.last_tick = now;
+
+        let mut net_rows: Vec<NetworkInfo> = Vec::new();
+        for (name, data) in self.networks.iter() {
+            let rx = data.total_received();
+            let tx = data.total_transmitted();
+            // Hidden interfaces keep their baseline current, so showing one doesn't report
+            // everything since it was hidden as a single interval
+            let (prev_rx, prev_tx) = self.last_net_totals.insert(name.to_string(), (rx, tx)).unwrap_or((rx, tx));
+            if !show_all && !self.filter.allows(name) {
+                continue;
+            }
+
+            net_rows.push(NetworkInfo {
+                name: name.to_string(),
+                rx_total: rx,
+                tx_total: tx,
+                rx_bps: (rx.saturating_sub(prev_rx)) as f64 / dt,
+                tx_bps: (tx.saturating_sub(prev_tx)) as f64 / dt,
+                master: bridge_master(name),
+            });
+        }
+
+        net_rows.sort_by(|a, b| b.name.cmp(&a.name));
+        group_by_bridge(net_rows)
+    }
+}
+
+/// Moves bridge ports right after their bridge, ports sorted by name.
+fn group_by_bridge(rows: Vec<NetworkInfo>) -> Vec<NetworkInfo> {
+    let (mut ports, top): (Vec<NetworkInfo>, Vec<NetworkInfo>) =
+        rows.iter().cloned().partition(|n| is_grouped_port(n, &rows));
+    ports.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut grouped = Vec::with_capacity(rows.len());
+    for bridge in top {
+        push_with_ports(bridge, &ports, &mut grouped);
+    }
+    // Only a loop of masters could leave ports unplaced, keep them rather than drop them
+    for port in ports {
+        if !grouped.iter().any(|n| n.name == port.name) {
+            grouped.push(port);
+        }
+    }
+    grouped
+}
+
+fn push_with_ports(row: NetworkInfo, ports: &[NetworkInfo], out: &mut Vec<NetworkInfo>) {
+    let name = row.name.clone();
+    out.push(row);
+    for port in ports.iter().filter(|p| p.master.as_deref() == Some(name.as_str())) {
+        // A bond can be a port of a bridge and have ports of its own
+        if !out.iter().any(|n| n.name == port.name) {
+            push_with_ports(port.clone(), ports, out);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn bridge_master(name: &str) -> Option<String> {
+    let link = std::fs::read_link(format!("/sys/class/net/{}/master", name)).ok()?;
+    link.file_name().map(|master| master.to_string_lossy().into_owned())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn bridge_master(_name: &str) -> Option<String> {
+    None
+}
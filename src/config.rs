@@ -0,0 +1,541 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Deserializer};
+
+use crate::cli::Args;
+use crate::units::{self, RateUnit, Units};
+use crate::SortBy;
+
+/// Settings from `~/.config/taskmanager-lite/config.toml`, overridden by command-line flags.
+/// Every key is optional, a missing key keeps the built-in default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Seconds between samples of the process and network collectors (and `--batch`)
+    pub interval: f64,
+    /// Seconds between redraws of the UI
+    pub ui_interval: f64,
+    /// Seconds between refreshes of the data behind `p <PID>`
+    pub detail_interval: f64,
+    /// Rows in the process table, 0 shows every process
+    pub max_processes: usize,
+    /// Rows in the network table
+    pub max_interfaces: usize,
+    #[serde(deserialize_with = "parse_str")]
+    pub sort: SortBy,
+    /// Start with the per-core CPU grid instead of only the aggregate graph
+    pub per_core: bool,
+    /// Process table columns, in order
+    pub columns: Columns,
+    pub panels: Panels,
+    pub thresholds: Thresholds,
+    pub interfaces: InterfaceFilter,
+    pub units: Units,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            interval: 1.0,
+            ui_interval: 0.1,
+            detail_interval: 2.0,
+            max_processes: 0,
+            max_interfaces: 6,
+            sort: SortBy::Cpu,
+            per_core: false,
+            columns: Columns::default(),
+            panels: Panels::default(),
+            thresholds: Thresholds::default(),
+            interfaces: InterfaceFilter::default(),
+            units: Units::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file (`--config` or the default path, which may be missing) and
+    /// applies the command-line overrides on top.
+    pub fn load(args: &Args) -> Result<Config, String> {
+        let mut config = match &args.config {
+            Some(path) => Config::from_file(path)?,
+            None => match default_path() {
+                Some(path) if path.exists() => Config::from_file(&path)?,
+                _ => Config::default(),
+            },
+        };
+        config.apply_args(args)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Config, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read config {}: {}", path.display(), e))?;
+        // toml's error already names the key and points at the line with a caret
+        toml::from_str(&text).map_err(|e| format!("invalid config {}:\n{}", path.display(), e.to_string().trim_end()))
+    }
+
+    fn apply_args(&mut self, args: &Args) -> Result<(), String> {
+        if let Some(interval) = args.interval {
+            self.interval = interval;
+        }
+        if let Some(interval) = args.ui_interval {
+            self.ui_interval = interval;
+        }
+        if let Some(interval) = args.detail_interval {
+            self.detail_interval = interval;
+        }
+        if let Some(max) = args.max_processes {
+            self.max_processes = max;
+        }
+        if let Some(max) = args.max_interfaces {
+            self.max_interfaces = max;
+        }
+        if let Some(sort) = args.sort {
+            self.sort = sort;
+        }
+        if args.per_core {
+            self.per_core = true;
+        }
+        if let Some(columns) = &args.columns {
+            self.columns = columns.clone();
+        }
+        if let Some(panels) = &args.panels {
+            self.panels = panels.clone();
+        }
+        for (name, value) in &args.thresholds {
+            self.thresholds.set(name, *value)?;
+        }
+        if !args.iface_include.is_empty() {
+            self.interfaces.include = args.iface_include.clone();
+        }
+        if !args.iface_exclude.is_empty() {
+            self.interfaces.exclude = args.iface_exclude.clone();
+        }
+        if let Some(system) = args.units {
+            self.units.system = system;
+        }
+        if let Some(precision) = args.precision {
+            self.units.precision = precision;
+        }
+        if args.bits {
+            self.units.network = RateUnit::Bits;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, secs) in [
+            ("interval", self.interval),
+            ("ui_interval", self.ui_interval),
+            ("detail_interval", self.detail_interval),
+        ] {
+            if !secs.is_finite() || secs <= 0.0 {
+                return Err(format!("{} must be a positive number of seconds, got {}", name, secs));
+            }
+        }
+        if self.units.precision > units::MAX_PRECISION {
+            return Err(format!(
+                "units precision must be at most {} decimals, got {}",
+                units::MAX_PRECISION,
+                self.units.precision
+            ));
+        }
+        self.thresholds.validate()
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs_f64(self.interval)
+    }
+
+    pub fn ui_interval(&self) -> Duration {
+        Duration::from_secs_f64(self.ui_interval)
+    }
+
+    pub fn detail_interval(&self) -> Duration {
+        Duration::from_secs_f64(self.detail_interval)
+    }
+
+    /// PSS/USS are shown or sorted on, so the collector has to read smaps_rollup
+    pub fn wants_smaps(&self) -> bool {
+        self.columns.0.iter().chain([&self.sort]).any(|c| c.needs_smaps())
+    }
+}
+
+/// `$XDG_CONFIG_HOME/taskmanager-lite/config.toml`, falling back to `~/.config`.
+pub fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("taskmanager-lite").join("config.toml"))
+}
+
+/// Which panels are drawn. Written as a list of names, e.g. `["system", "processes"]`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct Panels {
+    pub system: bool,
+    pub processes: bool,
+    pub memory: bool,
+    pub network: bool,
+    pub disks: bool,
+    pub command: bool,
+}
+
+impl Default for Panels {
+    fn default() -> Self {
+        Panels {
+            system: true,
+            processes: true,
+            memory: true,
+            network: true,
+            disks: true,
+            command: true,
+        }
+    }
+}
+
+impl TryFrom<Vec<String>> for Panels {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        let mut panels = Panels {
+            system: false,
+            processes: false,
+            memory: false,
+            network: false,
+            disks: false,
+            command: false,
+        };
+        for name in names {
+            let flag = match name.trim().to_lowercase().as_str() {
+                "system" => &mut panels.system,
+                "processes" => &mut panels.processes,
+                "memory" => &mut panels.memory,
+                "network" => &mut panels.network,
+                "disks" => &mut panels.disks,
+                "command" => &mut panels.command,
+                other => {
+                    return Err(format!(
+                        "unknown panel '{}', expected system, processes, memory, network, disks or command",
+                        other
+                    ))
+                }
+            };
+            *flag = true;
+        }
+        Ok(panels)
+    }
+}
+
+impl FromStr for Panels {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Panels::try_from(s.split(',').filter(|p| !p.trim().is_empty()).map(String::from).collect::<Vec<_>>())
+    }
+}
+
+/// Process table columns. Written as a list of sort names, e.g. `["name", "pid", "nice"]`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct Columns(pub Vec<SortBy>);
+
+impl Default for Columns {
+    fn default() -> Self {
+        Columns(SortBy::DEFAULT_COLUMNS.to_vec())
+    }
+}
+
+impl TryFrom<Vec<String>> for Columns {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        let mut columns = Vec::new();
+        for name in names {
+            let column: SortBy = name.parse()?;
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        if columns.is_empty() {
+            return Err("the process table needs at least one column".to_string());
+        }
+        Ok(Columns(columns))
+    }
+}
+
+impl FromStr for Columns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Columns::try_from(s.split(',').filter(|c| !c.trim().is_empty()).map(String::from).collect::<Vec<_>>())
+    }
+}
+
+/// Percentages at which values turn yellow (`*_warn`) or red (`*_crit`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thresholds {
+    pub cpu_warn: f32,
+    pub cpu_crit: f32,
+    /// Process rows using more than this share of RAM are drawn in magenta
+    pub process_memory: f64,
+    pub ram_warn: f64,
+    pub ram_crit: f64,
+    pub swap_warn: f64,
+    pub swap_crit: f64,
+    /// Open descriptors as a share of the soft RLIMIT_NOFILE
+    pub fd_warn: f64,
+    pub fd_crit: f64,
+    /// Share of the time a disk was busy (%util)
+    pub disk_warn: f64,
+    pub disk_crit: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            cpu_warn: 50.0,
+            cpu_crit: 80.0,
+            process_memory: 20.0,
+            ram_warn: 75.0,
+            ram_crit: 90.0,
+            swap_warn: 50.0,
+            swap_crit: 75.0,
+            fd_warn: 70.0,
+            fd_crit: 90.0,
+            disk_warn: 60.0,
+            disk_crit: 90.0,
+        }
+    }
+}
+
+impl Thresholds {
+    const NAMES: &'static str =
+        "cpu_warn, cpu_crit, process_memory, ram_warn, ram_crit, swap_warn, swap_crit, fd_warn, fd_crit, disk_warn or disk_crit";
+
+    fn set(&mut self, name: &str, value: f64) -> Result<(), String> {
+        match name {
+            "cpu_warn" => self.cpu_warn = value as f32,
+            "cpu_crit" => self.cpu_crit = value as f32,
+            "process_memory" => self.process_memory = value,
+            "ram_warn" => self.ram_warn = value,
+            "ram_crit" => self.ram_crit = value,
+            "swap_warn" => self.swap_warn = value,
+            "swap_crit" => self.swap_crit = value,
+            "fd_warn" => self.fd_warn = value,
+            "fd_crit" => self.fd_crit = value,
+            "disk_warn" => self.disk_warn = value,
+            "disk_crit" => self.disk_crit = value,
+            _ => return Err(format!("unknown threshold '{}', expected {}", name, Thresholds::NAMES)),
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let percentages = [
+            ("process_memory", self.process_memory),
+            ("ram_warn", self.ram_warn),
+            ("ram_crit", self.ram_crit),
+            ("swap_warn", self.swap_warn),
+            ("swap_crit", self.swap_crit),
+            ("fd_warn", self.fd_warn),
+            ("fd_crit", self.fd_crit),
+            ("disk_warn", self.disk_warn),
+            ("disk_crit", self.disk_crit),
+        ];
+        for (name, value) in percentages {
+            if !(0.0..=100.0).contains(&value) {
+                return Err(format!("threshold {} must be between 0 and 100, got {}", name, value));
+            }
+        }
+        // CPU % of one process goes past 100 on multi-core machines, so only the lower bound
+        for (name, value) in [("cpu_warn", self.cpu_warn), ("cpu_crit", self.cpu_crit)] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("threshold {} must be a positive percentage, got {}", name, value));
+            }
+        }
+        let pairs = [
+            ("cpu", self.cpu_warn as f64, self.cpu_crit as f64),
+            ("ram", self.ram_warn, self.ram_crit),
+            ("swap", self.swap_warn, self.swap_crit),
+            ("fd", self.fd_warn, self.fd_crit),
+            ("disk", self.disk_warn, self.disk_crit),
+        ];
+        for (name, warn, crit) in pairs {
+            if warn > crit {
+                return Err(format!("threshold {0}_warn ({1}) is above {0}_crit ({2})", name, warn, crit));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Network interfaces to list. An empty include list means every interface that no
+/// exclude pattern matches.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InterfaceFilter {
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+}
+
+impl Default for InterfaceFilter {
+    fn default() -> Self {
+        InterfaceFilter {
+            include: Vec::new(),
+            // Bridge ports (veth*, ...) are folded under their bridge instead of hidden
+            exclude: ["*npcap*", "*nocap*", "lo"]
+                .iter()
+                .filter_map(|p| p.parse().ok())
+                .collect(),
+        }
+    }
+}
+
+impl InterfaceFilter {
+    pub fn allows(&self, name: &str) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|p| p.matches(name));
+        included && !self.exclude.iter().any(|p| p.matches(name))
+    }
+}
+
+/// An interface name pattern. Globs (`*`, `?`, `[...]`) match the whole name, a `re:`
+/// prefix takes a regex that may match anywhere, like the process search. Both ignore case.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Pattern {
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn matches(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expr = match s.strip_prefix("re:") {
+            Some(expr) => expr.to_string(),
+            None => glob_to_regex(s)?,
+        };
+        RegexBuilder::new(&expr)
+            .case_insensitive(true)
+            .build()
+            .map(|regex| Pattern { regex })
+            .map_err(|e| format!("invalid pattern '{}': {}", s, e.to_string().lines().last().unwrap_or("bad regex")))
+    }
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+fn glob_to_regex(glob: &str) -> Result<String, String> {
+    let mut expr = String::from("^");
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => expr.push_str(".*"),
+            '?' => expr.push('.'),
+            '[' => {
+                let mut class = String::new();
+                loop {
+                    match chars.next() {
+                        // A `]` right after `[` or `[!` is part of the class
+                        Some(']') if !class.is_empty() && class != "!" => break,
+                        Some(c) => class.push(c),
+                        None => return Err(format!("invalid pattern '{}': unclosed '['", glob)),
+                    }
+                }
+                if let Some(rest) = class.strip_prefix('!') {
+                    class = format!("^{}", rest);
+                }
+                expr.push('[');
+                // `&&` and `~~` are set operations in a regex class, literal characters in a glob
+                for c in class.chars() {
+                    if matches!(c, '\\' | '[' | '&' | '~') {
+                        expr.push('\\');
+                    }
+                    expr.push(c);
+                }
+                expr.push(']');
+            }
+            c => expr.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    expr.push('$');
+    Ok(expr)
+}
+
+fn parse_str<'de, D, T>(d: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    String::deserialize(d)?.parse().map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, name: &str) -> bool {
+        pattern.parse::<Pattern>().unwrap().matches(name)
+    }
+
+    #[test]
+    fn globs_match_the_whole_name() {
+        assert!(matches("eth*", "eth0"));
+        assert!(matches("eth*", "ETH1"));
+        assert!(!matches("eth*", "veth0"));
+        assert!(matches("lo", "lo"));
+        assert!(!matches("lo", "lo0"));
+        assert!(matches("wl?0", "wlx0"));
+        assert!(!matches("wl?0", "wl0"));
+        assert!(matches("a.b", "a.b"));
+        assert!(!matches("a.b", "axb"));
+        assert!(matches("*(v)*", "br(v)1"));
+    }
+
+    #[test]
+    fn globs_with_classes() {
+        assert!(matches("[be]r0", "br0"));
+        assert!(!matches("[be]r0", "xr0"));
+        assert!(matches("eth[0-3]", "eth2"));
+        assert!(!matches("eth[0-3]", "eth4"));
+        assert!(matches("[!l]*", "eth0"));
+        assert!(!matches("[!l]*", "lo"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[!]]x", "ax"));
+        assert!(!matches("[!]]x", "]x"));
+        assert!(matches("[a\\]", "\\"));
+        assert!(matches("[a&&b]", "&"));
+        assert!(matches("[a~~b]", "~"));
+        assert!("eth[0".parse::<Pattern>().is_err());
+        assert!("[!]".parse::<Pattern>().is_err());
+    }
+
+    #[test]
+    fn regexes_match_anywhere() {
+        assert!(matches("re:^(en|wl)", "enp3s0"));
+        assert!(matches("re:^(en|wl)", "WLAN0"));
+        assert!(!matches("re:^(en|wl)", "veth0"));
+        assert!(matches("re:docker", "br-docker0"));
+        assert!("re:(".parse::<Pattern>().is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    symbols,
+    text::{Line, Span},
+    widgets::{LineGauge, Paragraph},
+    Frame,
+};
+
+use crate::config::Thresholds;
+use crate::CoreInfo;
+
+// Cell widths: "  7 ━━━━━━━━ 100% 3.40G" and, once that needs too many rows, "  7 ━━━━ 99"
+const FULL_CELL: u16 = 24;
+const COMPACT_CELL: u16 = 12;
+// Cells don't stretch past this on wide terminals with few cores
+const MAX_CELL: u16 = 48;
+// Grids taller than this switch to compact cells
+const MAX_FULL_ROWS: u16 = 6;
+
+/// Cell width and column count for `cores` cores in `width` columns.
+fn layout(cores: usize, width: u16) -> (u16, u16) {
+    let cores = cores.max(1) as u16;
+    let full_cols = (width / FULL_CELL).clamp(1, cores);
+    let cols = if cores.div_ceil(full_cols) <= MAX_FULL_ROWS {
+        full_cols
+    } else {
+        (width / COMPACT_CELL).clamp(1, cores)
+    };
+    ((width / cols).min(MAX_CELL), cols)
+}
+
+/// Rows the grid needs at `width`.
+pub fn grid_height(cores: usize, width: u16) -> u16 {
+    let (_, cols) = layout(cores, width);
+    (cores.max(1) as u16).div_ceil(cols)
+}
+
+/// One gauge per core, filled left to right then top to bottom. When `area` is too
+/// short for all of them the last cell says how many were left out.
+pub fn draw(f: &mut Frame, area: Rect, cores: &[CoreInfo], thresholds: &Thresholds) {
+    if cores.is_empty() {
+        f.render_widget(
+            Paragraph::new(Span::styled("Waiting for the first sample…", Style::default().fg(Color::DarkGray))),
+            area,
+        );
+        return;
+    }
+    let (cell_width, cols) = layout(cores.len(), area.width);
+    let compact = cell_width < FULL_CELL;
+    let label_width = cores.len().saturating_sub(1).to_string().len();
+    let capacity = (area.height * cols) as usize;
+    let shown = if cores.len() > capacity { capacity.saturating_sub(1) } else { cores.len() };
+    let cell_at = |i: usize| Rect {
+        x: area.x + (i as u16 % cols) * cell_width,
+        y: area.y + i as u16 / cols,
+        width: cell_width.saturating_sub(1),
+        height: 1,
+    };
+
+    if shown < cores.len() && capacity > 0 {
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                format!("+{} more", cores.len() - shown),
+                Style::default().fg(Color::DarkGray),
+            )),
+            cell_at(shown),
+        );
+    }
+    for (i, core) in cores.iter().enumerate().take(shown) {
+        let cell = cell_at(i);
+        let color = if core.usage > thresholds.cpu_crit {
+            Color::Red
+        } else if core.usage > thresholds.cpu_warn {
+            Color::Yellow
+        } else {
+            Color::Green
+        };
+        let value = if compact {
+            format!(" {:>3.0}", core.usage)
+        } else {
+            format!(" {:>3.0}% {}", core.usage, frequency(core.frequency))
+        };
+        let split = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(1), Constraint::Length(value.chars().count() as u16)])
+            .split(cell);
+        f.render_widget(
+            LineGauge::default()
+                .label(Span::styled(format!("{:>w$}", i, w = label_width), Style::default().fg(Color::Cyan)))
+                .ratio((core.usage as f64 / 100.0).clamp(0.0, 1.0))
+                .line_set(symbols::line::THICK)
+                .filled_style(Style::default().fg(color))
+                .unfilled_style(Style::default().fg(Color::DarkGray)),
+            split[0],
+        );
+        f.render_widget(Paragraph::new(Line::from(Span::styled(value, Style::default().fg(color)))), split[1]);
+    }
+}
+
+/// MHz as `3.40G` or `800M`, `-` when the platform doesn't report it.
+fn frequency(mhz: u64) -> String {
+    match mhz {
+        0 => "    -".to_string(),
+        1000.. => format!("{:.2}G", mhz as f64 / 1000.0),
+        _ => format!("{:>4}M", mhz),
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Paragraph, Row, Table},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Thresholds;
+use crate::units;
+
+// Per-device I/O from /proc/diskstats, the numbers `iostat -x` prints. Unlike the sum of
+// the per-process counters these include kernel writeback and swap.
+
+// /proc/diskstats counts in 512-byte sectors whatever the device's real sector size
+const SECTOR_SIZE: f64 = 512.0;
+
+/// One block device over the last sample.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DiskInfo {
+    pub name: String,
+    /// Whole device of a partition, e.g. `sda` for `sda1`
+    pub parent: Option<String>,
+    /// A whole device backed by hardware (or a virtio/xen disk) rather than device-mapper,
+    /// md, loop or zram. Their sum is the machine's disk I/O
+    pub physical: bool,
+    pub read_bps: f64,
+    pub write_bps: f64,
+    pub reads_per_sec: f64,
+    pub writes_per_sec: f64,
+    /// Milliseconds an I/O took on average, queueing included (iostat's `r_await`/`w_await`)
+    pub read_await: f64,
+    pub write_await: f64,
+    /// Average requests in flight (`aqu-sz`)
+    pub queue_depth: f64,
+    /// Share of the time the device was busy. Meaningless past 100% for SSDs and arrays
+    /// that serve requests in parallel, but still a saturation hint
+    pub util: f64,
+}
+
+/// The cumulative counters of one line of /proc/diskstats.
+#[derive(Clone, Copy)]
+struct Counters {
+    reads: u64,
+    sectors_read: u64,
+    read_ms: u64,
+    writes: u64,
+    sectors_written: u64,
+    write_ms: u64,
+    busy_ms: u64,
+    weighted_ms: u64,
+}
+
+/// Rates are computed against the previous `collect`.
+pub struct DiskCollector {
+    last: HashMap<String, Counters>,
+    last_tick: Instant,
+}
+
+impl DiskCollector {
+    pub fn new() -> Self {
+        DiskCollector {
+            last: read_counters().into_iter().collect(),
+            last_tick: Instant::now(),
+        }
+    }
+
+    pub fn collect(&mut self) -> Vec<DiskInfo> {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_tick).as_secs_f64().max(1e-9);
+        let dt_ms = dt * 1000.0;
+        let current = read_counters();
+
+        let mut disks: Vec<DiskInfo> = current
+            .iter()
+            .filter_map(|(name, c)| {
+                // A device that showed up since the last sample has no baseline yet
+                let prev = self.last.get(name)?;
+                // Unused loop and ram devices would only add empty rows
+                if c.reads == 0 && c.writes == 0 {
+                    return None;
+                }
+                let delta = |now: u64, before: u64| now.saturating_sub(before) as f64;
+                let reads = delta(c.reads, prev.reads);
+                let writes = delta(c.writes, prev.writes);
+                let (parent, physical) = device_kind(name);
+                Some(DiskInfo {
+                    name: name.clone(),
+                    parent,
+                    physical,
+                    read_bps: delta(c.sectors_read, prev.sectors_read) * SECTOR_SIZE / dt,
+                    write_bps: delta(c.sectors_written, prev.sectors_written) * SECTOR_SIZE / dt,
+                    reads_per_sec: reads / dt,
+                    writes_per_sec: writes / dt,
+                    read_await: if reads > 0.0 { delta(c.read_ms, prev.read_ms) / reads } else { 0.0 },
+                    write_await: if writes > 0.0 { delta(c.write_ms, prev.write_ms) / writes } else { 0.0 },
+                    queue_depth: delta(c.weighted_ms, prev.weighted_ms) / dt_ms,
+                    util: (delta(c.busy_ms, prev.busy_ms) / dt_ms * 100.0).min(100.0),
+                })
+            })
+            .collect();
+        // Partitions right after their device, in the kernel's numbering order
+        disks.sort_by(|a, b| {
+            let key = |d: &DiskInfo| (d.parent.clone().unwrap_or_else(|| d.name.clone()), d.parent.is_some());
+            key(a).cmp(&key(b)).then_with(|| natural_cmp(&a.name, &b.name))
+        });
+
+        self.last = current.into_iter().collect();
+        self.last_tick = now;
+        disks
+    }
+}
+
+/// Read and write rates of the physical devices, None when there are none (not Linux, or a
+/// container without block devices) and the caller has to fall back to the process sums.
+pub fn physical_totals(disks: &[DiskInfo]) -> Option<(f64, f64)> {
+    let physical: Vec<&DiskInfo> = disks.iter().filter(|d| d.physical && d.parent.is_none()).collect();
+    if physical.is_empty() {
+        return None;
+    }
+    Some((physical.iter().map(|d| d.read_bps).sum(), physical.iter().map(|d| d.write_bps).sum()))
+}
+
+/// `sda2` after `sda1` but `sda10` after `sda9`.
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    let split = |s: &str| {
+        let digits = s.len() - s.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        let (stem, number) = s.split_at(s.len() - digits);
+        (stem.to_string(), number.parse::<u64>().unwrap_or(0))
+    };
+    split(a).cmp(&split(b))
+}
+
+/// `8 0 sda 1234 ...`, fields as numbered in the kernel's iostats documentation.
+#[cfg(target_os = "linux")]
+fn read_counters() -> Vec<(String, Counters)> {
+    let Ok(text) = std::fs::read_to_string("/proc/diskstats") else {
+        return Vec::new();
+    };
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let field = |n: usize| fields.get(n + 2).and_then(|f| f.parse::<u64>().ok());
+            Some((
+                fields.get(2)?.to_string(),
+                Counters {
+                    reads: field(1)?,
+                    sectors_read: field(3)?,
+                    read_ms: field(4)?,
+                    writes: field(5)?,
+                    sectors_written: field(7)?,
+                    write_ms: field(8)?,
+                    busy_ms: field(10)?,
+                    weighted_ms: field(11)?,
+                },
+            ))
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_counters() -> Vec<(String, Counters)> {
+    Vec::new()
+}
+
+/// The parent of a partition and whether the device is physical, from sysfs. Partitions
+/// have a `partition` file and live in their device's directory, and only devices with a
+/// driver behind them have a `device` link.
+#[cfg(target_os = "linux")]
+fn device_kind(name: &str) -> (Option<String>, bool) {
+    let dir = std::path::Path::new("/sys/class/block").join(name);
+    if dir.join("partition").exists() {
+        let parent = std::fs::canonicalize(&dir)
+            .ok()
+            .and_then(|path| Some(path.parent()?.file_name()?.to_string_lossy().into_owned()));
+        return (parent, false);
+    }
+    (None, dir.join("device").exists())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn device_kind(_name: &str) -> (Option<String>, bool) {
+    (None, false)
+}
+
+/// Rows the panel wants for `disks`: the header, one row per listed device and the borders.
+pub fn panel_height(disks: &[DiskInfo], show_partitions: bool) -> u16 {
+    let rows = disks.iter().filter(|d| show_partitions || d.parent.is_none()).count();
+    (rows as u16 + 3).clamp(4, 12)
+}
+
+/// The Disks panel, `iostat -x` style. Partitions are folded into their device's row as
+/// `sda (+2)` unless `show_partitions` lists them under it.
+pub fn draw(f: &mut Frame, area: Rect, disks: &[DiskInfo], show_partitions: bool, thresholds: &Thresholds) {
+    let title = if show_partitions { "Disks (partitions, 'disks' to fold)" } else { "Disks" };
+    let block = Block::default().title(title).borders(Borders::ALL);
+    if disks.is_empty() {
+        let message = if cfg!(target_os = "linux") {
+            "Waiting for the first sample…"
+        } else {
+            "Per-device I/O needs /proc/diskstats (Linux only)"
+        };
+        f.render_widget(
+            Paragraph::new(message).style(Style::default().fg(Color::DarkGray)).block(block),
+            area,
+        );
+        return;
+    }
+
+    let rows: Vec<Row> = disks
+        .iter()
+        .filter(|d| show_partitions || d.parent.is_none())
+        .map(|d| {
+            let partitions = disks.iter().filter(|p| p.parent.as_ref() == Some(&d.name)).count();
+            let name = if d.parent.is_some() {
+                format!(" └{}", d.name)
+            } else if partitions > 0 && !show_partitions {
+                format!("{} (+{})", d.name, partitions)
+            } else {
+                d.name.clone()
+            };
+            let style = if d.util > thresholds.disk_crit {
+                Style::default().fg(Color::Red)
+            } else if d.util > thresholds.disk_warn {
+                Style::default().fg(Color::Yellow)
+            } else if d.parent.is_some() {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            Row::new(vec![
+                name,
+                units::rate(d.read_bps),
+                units::rate(d.write_bps),
+                format!("{:.1}", d.reads_per_sec),
+                format!("{:.1}", d.writes_per_sec),
+                format!("{:.2}", d.read_await),
+                format!("{:.2}", d.write_await),
+                format!("{:.2}", d.queue_depth),
+                format!("{:.1}%", d.util),
+            ])
+            .style(style)
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Fill(14),
+            Constraint::Fill(12),
+            Constraint::Fill(12),
+            Constraint::Fill(8),
+            Constraint::Fill(8),
+            Constraint::Fill(8),
+            Constraint::Fill(8),
+            Constraint::Fill(7),
+            Constraint::Fill(7),
+        ],
+    )
+    .header(
+        Row::new(vec!["Device", "Read/s", "Write/s", "r/s", "w/s", "r_await", "w_await", "aqu-sz", "%util"])
+            .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+    )
+    .block(block);
+    f.render_widget(table, area);
+}
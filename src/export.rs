@@ -0,0 +1,261 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::diskstats::DiskInfo;
+use crate::meminfo::MemInfo;
+use crate::{NetworkInfo, ProcessInfo, SharedState, TaskCounts};
+
+/// Serde helpers for `sysinfo::Pid`, which only implements `Serialize` upstream.
+pub mod pid_serde {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use sysinfo::Pid;
+
+    pub fn serialize<S: Serializer>(pid: &Pid, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u32(pid.as_u32())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Pid, D::Error> {
+        Ok(Pid::from_u32(u32::deserialize(d)?))
+    }
+}
+
+pub mod opt_pid_serde {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use sysinfo::Pid;
+
+    pub fn serialize<S: Serializer>(pid: &Option<Pid>, s: S) -> Result<S::Ok, S::Error> {
+        match pid {
+            Some(pid) => s.serialize_some(&pid.as_u32()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Pid>, D::Error> {
+        Ok(Option::<u32>::deserialize(d)?.map(Pid::from_u32))
+    }
+}
+
+#[derive(Serialize)]
+struct SystemTotals<'a> {
+    kernel: &'a str,
+    uptime: u64,
+    boot_time: u64,
+    load_average: [f64; 3],
+    tasks: TaskCounts,
+    cpu_model: &'a str,
+    total_cpu_usage: f32,
+    total_memory: u64,
+    used_memory: u64,
+    available_memory: u64,
+    total_swap: u64,
+    used_swap: u64,
+    disk_read_bps: f64,
+    disk_write_bps: f64,
+    /// The /proc/meminfo breakdown, Linux only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meminfo: Option<&'a MemInfo>,
+}
+
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    timestamp: String,
+    hostname: String,
+    system: SystemTotals<'a>,
+    network: &'a [NetworkInfo],
+    disks: &'a [DiskInfo],
+    processes: &'a [ProcessInfo],
+}
+
+impl<'a> Snapshot<'a> {
+    fn new(state: &'a SharedState) -> Self {
+        Snapshot {
+            timestamp: format_timestamp(SystemTime::now()),
+            hostname: state.host.hostname.clone(),
+            system: SystemTotals {
+                kernel: &state.host.kernel,
+                uptime: state.host.uptime,
+                boot_time: state.host.boot_time,
+                load_average: state.host.load_average,
+                tasks: TaskCounts::from_processes(&state.processes),
+                cpu_model: &state.cpu_model,
+                total_cpu_usage: state.total_cpu_usage,
+                total_memory: state.total_memory,
+                used_memory: state.used_memory,
+                available_memory: state.available_memory,
+                total_swap: state.total_swap,
+                used_swap: state.used_swap,
+                disk_read_bps: state.disk_read_bps,
+                disk_write_bps: state.disk_write_bps,
+                meminfo: state.meminfo.as_ref(),
+            },
+            network: &state.network_data,
+            disks: &state.disks,
+            processes: &state.processes,
+        }
+    }
+}
+
+/// Writes the current state to `path` as CSV or JSON, picked from the file extension.
+pub fn write_snapshot(path: &Path, state: &SharedState) -> io::Result<()> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    let snapshot = Snapshot::new(state);
+    match ext.as_deref() {
+        Some("json") => {
+            let mut out = BufWriter::new(File::create(path)?);
+            serde_json::to_writer_pretty(&mut out, &snapshot)?;
+            writeln!(out)?;
+            out.flush()
+        }
+        Some("csv") => {
+            let mut out = BufWriter::new(File::create(path)?);
+            write_csv(&mut out, &snapshot)?;
+            out.flush()
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "unknown export format, use a .csv or .json file name",
+        )),
+    }
+}
+
+// One CSV section per table, separated by blank lines, each with its own header row
+fn write_csv(out: &mut impl Write, snap: &Snapshot) -> io::Result<()> {
+    writeln!(out, "timestamp,hostname")?;
+    write_csv_row(out, &[snap.timestamp.clone(), snap.hostname.clone()])?;
+    writeln!(out)?;
+
+    let sys = &snap.system;
+    writeln!(
+        out,
+        "kernel,uptime,boot_time,load_1,load_5,load_15,tasks_total,tasks_running,tasks_sleeping,tasks_stopped,tasks_zombie,\
+         cpu_model,total_cpu_usage,total_memory,used_memory,available_memory,total_swap,used_swap,disk_read_bps,disk_write_bps"
+    )?;
+    write_csv_row(
+        out,
+        &[
+            sys.kernel.to_string(),
+            sys.uptime.to_string(),
+            sys.boot_time.to_string(),
+            format!("{:.2}", sys.load_average[0]),
+            format!("{:.2}", sys.load_average[1]),
+            format!("{:.2}", sys.load_average[2]),
+            sys.tasks.total.to_string(),
+            sys.tasks.running.to_string(),
+            sys.tasks.sleeping.to_string(),
+            sys.tasks.stopped.to_string(),
+            sys.tasks.zombie.to_string(),
+            sys.cpu_model.to_string(),
+            format!("{:.2}", sys.total_cpu_usage),
+            sys.total_memory.to_string(),
+            sys.used_memory.to_string(),
+            sys.available_memory.to_string(),
+            sys.total_swap.to_string(),
+            sys.used_swap.to_string(),
+            format!("{:.0}", sys.disk_read_bps),
+            format!("{:.0}", sys.disk_write_bps),
+        ],
+    )?;
+    writeln!(out)?;
+
+    writeln!(out, "interface,rx_total,tx_total,rx_bps,tx_bps")?;
+    for net in snap.network {
+        write_csv_row(
+            out,
+            &[
+                net.name.clone(),
+                net.rx_total.to_string(),
+                net.tx_total.to_string(),
+                format!("{:.0}", net.rx_bps),
+                format!("{:.0}", net.tx_bps),
+            ],
+        )?;
+    }
+    writeln!(out)?;
+
+    writeln!(out, "{}", PROCESS_CSV_HEADER)?;
+    for p in snap.processes {
+        write_csv_row(out, &process_csv_fields(p))?;
+    }
+    Ok(())
+}
+
+pub const PROCESS_CSV_HEADER: &str =
+    "pid,parent,name,user,status,cpu_usage,memory,threads,disk_read_bps,disk_write_bps,run_time,start_time,nice,io_priority,fds,pss,uss,is_thread,cmd";
+
+/// Raw values for one process, in `PROCESS_CSV_HEADER` order.
+pub fn process_csv_fields(p: &ProcessInfo) -> Vec<String> {
+    vec![
+        p.pid.to_string(),
+        p.parent.map_or(String::new(), |pid| pid.to_string()),
+        p.name.clone(),
+        p.user.clone(),
+        p.status.clone(),
+        format!("{:.2}", p.cpu_usage),
+        p.memory.to_string(),
+        p.threads.to_string(),
+        format!("{:.0}", p.disk_read_bps),
+        format!("{:.0}", p.disk_write_bps),
+        p.run_time.to_string(),
+        p.start_time.to_string(),
+        p.nice.map_or(String::new(), |n| n.to_string()),
+        p.io_priority.clone().unwrap_or_default(),
+        p.fd_count.map_or(String::new(), |n| n.to_string()),
+        p.pss.map_or(String::new(), |n| n.to_string()),
+        p.uss.map_or(String::new(), |n| n.to_string()),
+        p.is_thread.to_string(),
+        p.cmd.clone(),
+    ]
+}
+
+/// The JSON export on a single line, for NDJSON output.
+pub fn snapshot_json_line(state: &SharedState) -> serde_json::Result<String> {
+    serde_json::to_string(&Snapshot::new(state))
+}
+
+pub fn write_csv_row(out: &mut impl Write, fields: &[String]) -> io::Result<()> {
+    let escaped: Vec<String> = fields.iter().map(|f| csv_escape(f)).collect();
+    writeln!(out, "{}", escaped.join(","))
+}
+
+pub fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// RFC 3339 timestamp in UTC, e.g. `2024-05-01T12:34:56Z`.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// How far back the histories reach, whatever the sampling interval.
+pub const HISTORY_WINDOW: Duration = Duration::from_secs(600);
+
+/// Samples it takes to cover `HISTORY_WINDOW` at one sample every `interval`.
+pub fn capacity(interval: Duration) -> usize {
+    (HISTORY_WINDOW.as_secs_f64() / interval.as_secs_f64().max(0.001)).ceil().max(2.0) as usize
+}
+
+/// Fixed-size ring buffer of samples taken `interval` apart, oldest first.
+#[derive(Clone)]
+pub struct History {
+    samples: VecDeque<f64>,
+    capacity: usize,
+    interval: Duration,
+}
+
+impl History {
+    pub fn new(interval: Duration) -> Self {
+        let capacity = capacity(interval);
+        History {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            interval,
+        }
+    }
+
+    pub fn push(&mut self, value: f64) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Seconds covered by `samples` consecutive samples.
+    pub fn seconds(&self, samples: usize) -> f64 {
+        samples as f64 * self.interval.as_secs_f64()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = f64> + ExactSizeIterator + '_ {
+        self.samples.iter().copied()
+    }
+
+    pub fn max(&self) -> f64 {
+        self.iter().fold(0.0, f64::max)
+    }
+
+    /// The newest `n` samples scaled to integers, for `Sparkline`.
+    pub fn last_scaled(&self, n: usize, scale: f64) -> Vec<u64> {
+        let skip = self.len().saturating_sub(n);
+        self.iter().skip(skip).map(|v| (v * scale).max(0.0) as u64).collect()
+    }
+
+    pub fn last(&self) -> Option<f64> {
+        self.samples.back().copied()
+    }
+
+    /// Least-squares slope in units per second.
+    pub fn slope(&self) -> Option<f64> {
+        let n = self.len();
+        if n < 2 {
+            return None;
+        }
+        let mean_x = (n as f64 - 1.0) / 2.0;
+        let mean_y = self.iter().sum::<f64>() / n as f64;
+        let (mut cov, mut var) = (0.0, 0.0);
+        for (i, y) in self.iter().enumerate() {
+            let dx = i as f64 - mean_x;
+            cov += dx * (y - mean_y);
+            var += dx * dx;
+        }
+        Some(cov / var / self.interval.as_secs_f64())
+    }
+
+    /// `(seconds ago, value)` points for `Chart`, with the newest sample at x = 0.
+    pub fn points(&self) -> Vec<(f64, f64)> {
+        let len = self.len();
+        self.iter()
+            .enumerate()
+            .map(|(i, v)| (self.seconds(i) - self.seconds(len.saturating_sub(1)), v))
+            .collect()
+    }
+}
+
+/// History of the global numbers in `SharedState`.
+pub struct MetricHistory {
+    pub cpu: History,
+    pub memory: History,
+    pub swap: History,
+    pub disk_read: History,
+    pub disk_write: History,
+    pub net_rx: History,
+    pub net_tx: History,
+}
+
+impl MetricHistory {
+    pub fn new(interval: Duration) -> Self {
+        MetricHistory {
+            cpu: History::new(interval),
+            memory: History::new(interval),
+            swap: History::new(interval),
+            disk_read: History::new(interval),
+            disk_write: History::new(interval),
+            net_rx: History::new(interval),
+            net_tx: History::new(interval),
+        }
+    }
+
+    /// Time between samples, which per-process histories are kept at as well.
+    pub fn interval(&self) -> Duration {
+        self.cpu.interval
+    }
+}
+
+/// Rolling CPU and RSS history for one PID, kept while the process is alive.
+pub struct ProcessHistory {
+    pub name: String,
+    pub cpu: History,
+    pub memory: History,
+    pub peak_cpu: f32,
+    pub peak_memory: u64,
+}
+
+impl ProcessHistory {
+    pub fn new(name: &str, interval: Duration) -> Self {
+        ProcessHistory {
+            name: name.to_string(),
+            cpu: History::new(interval),
+            memory: History::new(interval),
+            peak_cpu: 0.0,
+            peak_memory: 0,
+        }
+    }
+
+    pub fn push(&mut self, cpu: f32, memory: u64) {
+        self.cpu.push(cpu as f64);
+        self.memory.push(memory as f64);
+        self.peak_cpu = self.peak_cpu.max(cpu);
+        self.peak_memory = self.peak_memory.max(memory);
+    }
+}
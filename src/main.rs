@@ -1,9 +1,13 @@
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style, Modifier},
     text::{Line, Span},
-    widgets::{Block, Borders, Row, Table},
+    symbols,
+    widgets::{
+        Axis, Block, Borders, Cell, Chart, Dataset, GraphType, Row, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Sparkline, Table, TableState,
+    },
     Terminal,
 };
 use crossterm::{
@@ -11,36 +15,255 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use sysinfo::{System, Networks, Pid};
+use serde::{Deserialize, Serialize};
+use sysinfo::{Groups, System, Pid, Signal, Users};
 #[cfg(feature = "gpu")]
 use nvml_wrapper::Nvml;
 use std::time::{Duration, Instant};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+mod batch;
+mod cli;
+mod collector;
+mod config;
+mod cores;
+mod detail;
+mod diskstats;
+mod export;
+mod history;
+mod meminfo;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod proc_control;
+mod procfs;
+mod record;
+mod search;
+mod threads;
+mod tree;
+mod units;
+
+use collector::{NetworkCollector, ProcessCollector};
+use detail::{DetailTab, DetailView};
+use config::Config;
+use history::{MetricHistory, ProcessHistory};
+use search::Search;
+use tree::TreeRow;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum SortBy {
+    Name,
+    Pid,
+    User,
     Cpu,
     Memory,
-    Pid,
+    Threads,
+    DiskIo,
+    Status,
+    Runtime,
+    Nice,
+    IoPriority,
+    Parent,
+    StartTime,
+    Command,
+    Fds,
+    Pss,
+    Uss,
+}
+
+impl SortBy {
+    // Every process table column, in the order `col` lists them
+    const ALL: [SortBy; 17] = [
+        SortBy::Name,
+        SortBy::Pid,
+        SortBy::User,
+        SortBy::Cpu,
+        SortBy::Memory,
+        SortBy::Threads,
+        SortBy::DiskIo,
+        SortBy::Status,
+        SortBy::Runtime,
+        SortBy::Nice,
+        SortBy::IoPriority,
+        SortBy::Parent,
+        SortBy::StartTime,
+        SortBy::Command,
+        SortBy::Fds,
+        SortBy::Pss,
+        SortBy::Uss,
+    ];
+
+    // Columns shown unless the config or `col` says otherwise
+    const DEFAULT_COLUMNS: [SortBy; 9] = [
+        SortBy::Name,
+        SortBy::Pid,
+        SortBy::User,
+        SortBy::Cpu,
+        SortBy::Memory,
+        SortBy::Threads,
+        SortBy::DiskIo,
+        SortBy::Status,
+        SortBy::Runtime,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            SortBy::Name => "Name",
+            SortBy::Pid => "PID",
+            SortBy::User => "User",
+            SortBy::Cpu => "CPU %",
+            SortBy::Memory => "Memory",
+            SortBy::Threads => "Threads",
+            SortBy::DiskIo => "Disk I/O",
+            SortBy::Status => "Status",
+            SortBy::Runtime => "Runtime",
+            SortBy::Nice => "Nice",
+            SortBy::IoPriority => "I/O Prio",
+            SortBy::Parent => "PPID",
+            SortBy::StartTime => "Started",
+            SortBy::Command => "Command",
+            SortBy::Fds => "FDs",
+            SortBy::Pss => "PSS",
+            SortBy::Uss => "USS",
+        }
+    }
+
+    /// Share of the table width, the visible columns split the row by these weights
+    fn width(self) -> u16 {
+        match self {
+            SortBy::Name => 22,
+            SortBy::Pid => 7,
+            SortBy::User => 9,
+            SortBy::Cpu => 8,
+            SortBy::Memory => 15,
+            SortBy::Threads => 7,
+            SortBy::DiskIo => 12,
+            SortBy::Status => 9,
+            SortBy::Runtime => 11,
+            SortBy::Nice => 5,
+            SortBy::IoPriority => 8,
+            SortBy::Parent => 7,
+            SortBy::StartTime => 20,
+            SortBy::Command => 40,
+            SortBy::Fds => 6,
+            SortBy::Pss | SortBy::Uss => 10,
+        }
+    }
+
+    /// PSS/USS come from smaps_rollup, which is only read while one of them is shown or sorted on
+    fn needs_smaps(self) -> bool {
+        matches!(self, SortBy::Pss | SortBy::Uss)
+    }
+
+    /// Likewise nice and I/O priority, which cost a read and a syscall per process
+    fn needs_priorities(self) -> bool {
+        matches!(self, SortBy::Nice | SortBy::IoPriority)
+    }
+
+    /// And the descriptor count, a directory listing per process
+    fn needs_fd_counts(self) -> bool {
+        self == SortBy::Fds
+    }
+
+    /// Numbers sort biggest first, text and PIDs smallest first
+    fn default_descending(self) -> bool {
+        !matches!(
+            self,
+            SortBy::Name
+                | SortBy::Pid
+                | SortBy::User
+                | SortBy::Status
+                | SortBy::Nice
+                | SortBy::IoPriority
+                | SortBy::Parent
+                | SortBy::Command
+        )
+    }
+
+    /// Next or previous of the visible `columns`
+    fn cycle(self, forward: bool, columns: &[SortBy]) -> SortBy {
+        let Some(i) = columns.iter().position(|s| *s == self) else {
+            return columns.first().copied().unwrap_or(self);
+        };
+        let len = columns.len();
+        columns[if forward { (i + 1) % len } else { (i + len - 1) % len }]
+    }
 }
 
+impl std::str::FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "name" => Ok(SortBy::Name),
+            "pid" => Ok(SortBy::Pid),
+            "user" => Ok(SortBy::User),
+            "cpu" => Ok(SortBy::Cpu),
+            "memory" | "mem" => Ok(SortBy::Memory),
+            "threads" => Ok(SortBy::Threads),
+            "disk" | "diskio" | "io" => Ok(SortBy::DiskIo),
+            "status" => Ok(SortBy::Status),
+            "runtime" | "time" => Ok(SortBy::Runtime),
+            "nice" => Ok(SortBy::Nice),
+            "ioprio" | "ionice" => Ok(SortBy::IoPriority),
+            "ppid" | "parent" => Ok(SortBy::Parent),
+            "start" | "started" => Ok(SortBy::StartTime),
+            "cmd" | "command" => Ok(SortBy::Command),
+            "fds" | "fd" => Ok(SortBy::Fds),
+            "pss" => Ok(SortBy::Pss),
+            "uss" => Ok(SortBy::Uss),
+            _ => Err(format!(
+                "unknown column '{}', expected name, pid, user, cpu, memory, threads, disk, status, runtime, nice, \
+                 ioprio, ppid, start, cmd, fds, pss or uss",
+                s
+            )),
+        }
+    }
+}
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct ProcessInfo {
     name: String,
+    #[serde(with = "export::pid_serde")]
     pid: Pid,
     cpu_usage: f32,
     memory: u64,
     status: String,
     run_time: u64,
+    #[serde(with = "export::opt_pid_serde")]
+    parent: Option<Pid>,
+    is_thread: bool,
+    cmd: String,
+    user: String,
+    threads: usize,
+    disk_read_bps: f64,
+    disk_write_bps: f64,
+    /// None where the platform doesn't expose it (and in older recordings)
+    #[serde(default)]
+    nice: Option<i32>,
+    /// `be/4`, `rt/0`, `idle` or `none`
+    #[serde(default)]
+    io_priority: Option<String>,
+    /// Seconds since the epoch
+    #[serde(default)]
+    start_time: u64,
+    /// Open file descriptors, None when /proc/<pid>/fd isn't readable
+    #[serde(default)]
+    fd_count: Option<usize>,
+    /// Proportional and unique set size, only collected while a PSS/USS column is in use
+    #[serde(default)]
+    pss: Option<u64>,
+    #[serde(default)]
+    uss: Option<u64>,
 }
 
 struct SharedState {
     processes: Vec<ProcessInfo>,
+    host: HostInfo,
     cpu_model: String,
     total_cpu_usage: f32,
+    cores: Vec<CoreInfo>,
     total_memory: u64,
     used_memory: u64,
     available_memory: u64,
@@ -48,201 +271,583 @@ struct SharedState {
     used_swap: u64,
     disk_read_bps: f64,
     disk_write_bps: f64,
-    network_data: Vec<(String, String, String, String, String)>,
+    network_data: Vec<NetworkInfo>,
+    meminfo: Option<meminfo::MemInfo>,
+    disks: Vec<diskstats::DiskInfo>,
+    history: MetricHistory,
+    process_history: HashMap<Pid, ProcessHistory>,
     paused: bool,
+    /// Set while `--record` is writing samples, shown in the System panel
+    recording: Option<String>,
+    /// Set when playing back a recording instead of collecting live data
+    replay: Option<record::ReplayState>,
+    /// `ifaces all`: list every interface, ignoring the include/exclude patterns
+    show_all_interfaces: bool,
+    /// A PSS/USS column is shown or sorted on, the process thread reads smaps_rollup then
+    want_smaps: bool,
+    /// A Nice or I/O priority column is shown or sorted on
+    want_priorities: bool,
+    /// The FDs column is shown or sorted on
+    want_fd_counts: bool,
+}
+
+impl SharedState {
+    /// `interval` is the time between samples, which the histories are sized by.
+    fn new(interval: Duration) -> Self {
+        SharedState {
+            processes: Vec::new(),
+            host: HostInfo::default(),
+            cpu_model: String::new(),
+            total_cpu_usage: 0.0,
+            cores: Vec::new(),
+            total_memory: 0,
+            used_memory: 0,
+            available_memory: 0,
+            total_swap: 0,
+            used_swap: 0,
+            disk_read_bps: 0.0,
+            disk_write_bps: 0.0,
+            network_data: Vec::new(),
+            meminfo: None,
+            disks: Vec::new(),
+            history: MetricHistory::new(interval),
+            process_history: HashMap::new(),
+            paused: false,
+            recording: None,
+            replay: None,
+            show_all_interfaces: false,
+            want_smaps: false,
+            want_priorities: false,
+            want_fd_counts: false,
+        }
+    }
+}
+
+/// The `top` header line: who and what the machine is, and how long it has been up.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct HostInfo {
+    hostname: String,
+    kernel: String,
+    /// Seconds
+    uptime: u64,
+    /// Unix time in seconds
+    boot_time: u64,
+    /// 1, 5 and 15 minute load averages
+    load_average: [f64; 3],
+}
+
+/// Processes by state, like `top`'s Tasks line. Threads are not counted.
+#[derive(Clone, Copy, Default, Serialize)]
+struct TaskCounts {
+    total: usize,
+    running: usize,
+    sleeping: usize,
+    stopped: usize,
+    zombie: usize,
+}
+
+impl TaskCounts {
+    fn from_processes(procs: &[ProcessInfo]) -> Self {
+        let mut counts = TaskCounts::default();
+        for p in procs.iter().filter(|p| !p.is_thread) {
+            counts.total += 1;
+            // `status` holds the Debug name of sysinfo's ProcessStatus
+            match p.status.as_str() {
+                "Run" => counts.running += 1,
+                "Sleep" | "Idle" | "UninterruptibleDiskSleep" | "Parked" | "LockBlocked" => counts.sleeping += 1,
+                "Stop" | "Tracing" => counts.stopped += 1,
+                "Zombie" | "Dead" => counts.zombie += 1,
+                _ => {}
+            }
+        }
+        counts
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CoreInfo {
+    usage: f32,
+    /// MHz, 0 where the platform doesn't report it
+    frequency: u64,
 }
 
-fn bytes_to_human(b: u64) -> String {
-    const KB: f64 = 1024.0;
-    const MB: f64 = KB * 1024.0;
-    const GB: f64 = MB * 1024.0;
-    const TB: f64 = GB * 1024.0;
-    let bf = b as f64;
-    if bf >= TB {
-        format!("{:.1} TB", bf / TB)
-    } else if bf >= GB {
-        format!("{:.1} GB", bf / GB)
-    } else if bf >= MB {
-        format!("{:.1} MB", bf / MB)
-    } else if bf >= KB {
-        format!("{:.1} KB", bf / KB)
+#[derive(Clone, Serialize, Deserialize)]
+struct NetworkInfo {
+    name: String,
+    rx_total: u64,
+    tx_total: u64,
+    rx_bps: f64,
+    tx_bps: f64,
+    /// Bridge this interface is a port of, from `/sys/class/net/<name>/master`
+    #[serde(default)]
+    master: Option<String>,
+}
+
+/// Uptime like `3d 4h 12m`, or `12m` for short ones.
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
     } else {
-        format!("{} B", b)
+        format!("{}m", minutes)
     }
 }
 
-fn bytes_per_sec_human(bps: f64) -> String {
-    if bps.is_nan() || !bps.is_finite() {
-        return "0 B/s".to_string();
+/// Length of a history window, e.g. `90s` or `10m`.
+fn history_span(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    if seconds >= 120 {
+        format!("{}m", seconds / 60)
+    } else {
+        format!("{}s", seconds)
     }
-    let s = bytes_to_human(bps.max(0.0) as u64);
-    format!("{}/s", s)
 }
 
-fn main() -> std::io::Result<()> {
-    let shared_state = Arc::new(Mutex::new(SharedState {
-        processes: Vec::new(),
-        cpu_model: String::new(),
-        total_cpu_usage: 0.0,
-        total_memory: 0,
-        used_memory: 0,
-        available_memory: 0,
-        total_swap: 0,
-        used_swap: 0,
-        disk_read_bps: 0.0,
-        disk_write_bps: 0.0,
-        network_data: Vec::new(),
-        paused: false,
-    }));
-
-    let state_for_process = Arc::clone(&shared_state);
-    let state_for_network = Arc::clone(&shared_state);
+fn sort_processes(procs: &mut [ProcessInfo], sort_by: SortBy, descending: bool) {
+    use std::cmp::Ordering;
+    procs.sort_by(|a, b| {
+        let ord = match sort_by {
+            SortBy::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortBy::Pid => a.pid.cmp(&b.pid),
+            SortBy::User => a.user.cmp(&b.user),
+            SortBy::Cpu => a.cpu_usage.partial_cmp(&b.cpu_usage).unwrap_or(Ordering::Equal),
+            SortBy::Memory => a.memory.cmp(&b.memory),
+            SortBy::Threads => a.threads.cmp(&b.threads),
+            SortBy::DiskIo => (a.disk_read_bps + a.disk_write_bps)
+                .partial_cmp(&(b.disk_read_bps + b.disk_write_bps))
+                .unwrap_or(Ordering::Equal),
+            SortBy::Status => a.status.cmp(&b.status),
+            SortBy::Runtime => a.run_time.cmp(&b.run_time),
+            SortBy::Nice => a.nice.cmp(&b.nice),
+            SortBy::IoPriority => io_priority_rank(&a.io_priority).cmp(&io_priority_rank(&b.io_priority)),
+            SortBy::Parent => a.parent.cmp(&b.parent),
+            SortBy::StartTime => a.start_time.cmp(&b.start_time),
+            SortBy::Command => a.cmd.cmp(&b.cmd),
+            SortBy::Fds => a.fd_count.cmp(&b.fd_count),
+            SortBy::Pss => a.pss.cmp(&b.pss),
+            SortBy::Uss => a.uss.cmp(&b.uss),
+        };
+        let ord = if descending { ord.reverse() } else { ord };
+        // Ties always go by ascending PID so rows don't jump around between refreshes
+        ord.then_with(|| a.pid.cmp(&b.pid))
+    });
+}
+
+/// Runs `nice`/`ionice` for the command line and the F7/F8 keys, returning the message
+/// for the command output.
+fn renice(pid: Pid, nice: i32) -> String {
+    match proc_control::set_nice(pid, nice) {
+        Ok(changes) => format!("Set nice of PID {} to {} ({})", pid, nice, thread_summary(&changes)),
+        Err(e) => {
+            let hint = match e.kind() {
+                std::io::ErrorKind::PermissionDenied => ", lowering nice or changing other users' processes needs root or CAP_SYS_NICE",
+                _ => "",
+            };
+            format!("Failed to set nice of PID {}: {}{}", pid, proc_control::describe_os_error(&e), hint)
+        }
+    }
+}
+
+fn set_io_priority(pid: Pid, class: proc_control::IoClass, level: u8) -> String {
+    let label = proc_control::format_io_priority(class, level);
+    match proc_control::set_io_priority(pid, class, level) {
+        Ok(changes) => format!("Set I/O priority of PID {} to {} ({})", pid, label, thread_summary(&changes)),
+        Err(e) => {
+            let hint = match e.kind() {
+                std::io::ErrorKind::PermissionDenied if class == proc_control::IoClass::Realtime => {
+                    ", the realtime class needs root or CAP_SYS_ADMIN"
+                }
+                std::io::ErrorKind::PermissionDenied => ", changing other users' processes needs root",
+                _ => "",
+            };
+            format!("Failed to set I/O priority of PID {}: {}{}", pid, proc_control::describe_os_error(&e), hint)
+        }
+    }
+}
+
+/// `3 threads`, or `changed 3 of 5 threads, 2 denied (EPERM)` when some refused the change.
+fn thread_summary(changes: &proc_control::ThreadChanges) -> String {
+    match &changes.failed {
+        None => format!("{} thread{}", changes.changed, plural(changes.changed)),
+        Some((e, failed)) => {
+            let verb = if e.kind() == std::io::ErrorKind::PermissionDenied { "denied" } else { "failed" };
+            let reason = proc_control::errno_name(e).map_or_else(|| proc_control::describe_os_error(e), str::to_string);
+            format!(
+                "changed {} of {} thread{}, {} {} ({})",
+                changes.changed,
+                changes.total,
+                plural(changes.total),
+                failed,
+                verb,
+                reason
+            )
+        }
+    }
+}
+
+/// `taskset <PID>`: the affinity of the process, and of each thread whose mask differs.
+fn affinity_report(pid: Pid, total_cpus: usize) -> Vec<String> {
+    let cpus = match proc_control::cpu_affinity(pid) {
+        Ok(cpus) => cpus,
+        Err(e) => return vec![format!("Failed to read the CPU affinity of PID {}: {}", pid, proc_control::describe_os_error(&e))],
+    };
+    let mut lines = vec![format!(
+        "PID {} may run on CPUs {} ({} of {})",
+        pid,
+        proc_control::format_cpu_list(&cpus),
+        cpus.len(),
+        total_cpus
+    )];
+    let threads = proc_control::thread_ids(pid);
+    let differing: Vec<(Pid, Vec<usize>)> = threads
+        .iter()
+        .filter_map(|tid| Some((*tid, proc_control::cpu_affinity(*tid).ok()?)))
+        .filter(|(_, mask)| *mask != cpus)
+        .collect();
+    if differing.is_empty() {
+        lines.push(format!("  Same mask on all threads ({})", threads.len()));
+    }
+    for (tid, mask) in differing {
+        lines.push(format!("  TID {}: {}", tid, proc_control::format_cpu_list(&mask)));
+    }
+    lines
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Orders I/O priorities from most to least urgent: rt/0..rt/7, be/0..be/7, idle.
+/// `none` follows the nice value, which is be/4 for most processes.
+fn io_priority_rank(prio: &Option<String>) -> Option<u8> {
+    let prio = prio.as_deref()?;
+    let (class, level) = prio.split_once('/').unwrap_or((prio, "4"));
+    let level: u8 = level.parse().unwrap_or(4);
+    match class {
+        "rt" => Some(level),
+        "be" | "none" => Some(8 + level),
+        _ => Some(16),
+    }
+}
+
+fn process_details(sys: &System, pid: Pid) -> Option<Vec<String>> {
+    let proc = sys.process(pid)?;
+    let users = Users::new_with_refreshed_list();
+    let groups = Groups::new_with_refreshed_list();
+    let user_name = |uid: Option<&sysinfo::Uid>| match uid {
+        Some(uid) => match users.get_user_by_id(uid) {
+            Some(user) => format!("{} ({})", user.name(), **uid),
+            None => (**uid).to_string(),
+        },
+        None => "-".to_string(),
+    };
+    let group_name = |gid: Option<sysinfo::Gid>| match gid {
+        Some(gid) => match groups.iter().find(|g| *g.id() == gid) {
+            Some(group) => format!("{} ({})", group.name(), *gid),
+            None => (*gid).to_string(),
+        },
+        None => "-".to_string(),
+    };
+    let parent = match proc.parent() {
+        Some(ppid) => match sys.process(ppid) {
+            Some(p) => format!("{} ({})", ppid, p.name()),
+            None => ppid.to_string(),
+        },
+        None => "-".to_string(),
+    };
+
+    let mut lines = vec![
+        format!("Process Details for PID {}:", pid),
+        format!("  Name: {}", proc.name()),
+        format!("  Command: {}", if proc.cmd().is_empty() { "-".to_string() } else { proc.cmd().join(" ") }),
+        format!("  Status: {:?}", proc.status()),
+        format!("  User: {}", user_name(proc.user_id())),
+        format!("  Group: {}", group_name(proc.group_id())),
+    ];
+    // Only worth a line when setuid/setgid made them differ
+    if proc.effective_user_id().is_some() && proc.effective_user_id() != proc.user_id() {
+        lines.push(format!("  Effective User: {}", user_name(proc.effective_user_id())));
+    }
+    if proc.effective_group_id().is_some() && proc.effective_group_id() != proc.group_id() {
+        lines.push(format!("  Effective Group: {}", group_name(proc.effective_group_id())));
+    }
+    lines.extend([
+        format!("  Parent: {}", parent),
+        format!("  Session: {}", proc.session_id().map_or("-".to_string(), |sid| sid.to_string())),
+        format!(
+            "  Started: {}",
+            export::format_timestamp(std::time::UNIX_EPOCH + Duration::from_secs(proc.start_time()))
+        ),
+        format!("  CPU Usage: {:.2}%", proc.cpu_usage()),
+        format!("  Memory: {}", units::bytes(proc.memory())),
+        format!("  Virtual Memory: {}", units::bytes(proc.virtual_memory())),
+    ]);
+    if let Ok(usage) = procfs::memory_usage(pid) {
+        lines.push(format!("  PSS / USS: {} / {}", units::bytes(usage.pss), units::bytes(usage.uss())));
+        lines.push(format!("  Swap: {}", units::bytes(usage.swap)));
+    }
+    lines.extend([
+        format!("  Runtime: {} seconds", proc.run_time()),
+        format!("  Disk Read: {}", units::bytes(proc.disk_usage().total_read_bytes)),
+        format!("  Disk Write: {}", units::bytes(proc.disk_usage().total_written_bytes)),
+    ]);
+    if let Ok(cpus) = proc_control::cpu_affinity(pid) {
+        lines.push(format!(
+            "  Allowed CPUs: {} ({} of {})",
+            proc_control::format_cpu_list(&cpus),
+            cpus.len(),
+            sys.cpus().len()
+        ));
+    }
+    if let Some(fds) = procfs::fd_count(pid) {
+        let limit = |l: Option<u64>| l.map_or("unlimited".to_string(), |l| l.to_string());
+        lines.push(match procfs::fd_limits(pid) {
+            Ok(limits) => format!("  Open FDs: {} (limit {}/{})", fds, limit(limits.soft), limit(limits.hard)),
+            Err(_) => format!("  Open FDs: {}", fds),
+        });
+    }
+    if let Some(cwd) = proc.cwd() {
+        lines.push(format!("  CWD: {}", cwd.display()));
+    }
+    if let Some(exe) = proc.exe() {
+        lines.push(format!("  Executable: {}", exe.display()));
+    }
+
+    let environ = proc.environ();
+    if environ.is_empty() {
+        // /proc/<pid>/environ is only readable by the owner and root
+        lines.push("  Environment: not readable (needs the same user or root)".to_string());
+    } else {
+        lines.push(format!("  Environment ({} variables, secrets masked):", environ.len()));
+        let mut environ: Vec<String> = environ.iter().map(|var| detail::mask_env(var)).collect();
+        environ.sort();
+        lines.extend(environ.into_iter().map(|var| format!("    {}", var)));
+    }
+    Some(lines)
+}
+
+/// Details for `p <PID>` and Enter. Live processes are looked up fresh through sysinfo,
+/// during a replay the recorded `ProcessInfo` is all there is.
+fn details_for(pid: Pid, local_sys: &mut System, shared_state: &Mutex<SharedState>) -> Option<Vec<String>> {
+    let state = shared_state.lock().ok()?;
+    if state.replay.is_some() {
+        let p = state.processes.iter().find(|p| p.pid == pid)?;
+        return Some(vec![
+            format!("Recorded details for PID {}:", pid),
+            format!("  Name: {}", p.name),
+            format!("  User: {}", p.user),
+            format!("  Status: {}", p.status),
+            format!("  CPU Usage: {:.2}%", p.cpu_usage),
+            format!("  Memory: {}", units::bytes(p.memory)),
+            format!("  Threads: {}", p.threads),
+            format!("  Runtime: {} seconds", p.run_time),
+            format!("  Started: {}", export::format_timestamp(std::time::UNIX_EPOCH + Duration::from_secs(p.start_time))),
+            format!("  Parent: {}", p.parent.map_or("-".to_string(), |pid| pid.to_string())),
+            format!("  Command: {}", p.cmd),
+        ]);
+    }
+    drop(state);
+    local_sys.refresh_all();
+    process_details(local_sys, pid)
+}
+
+/// Opens the detail view of `pid` on `tab`, None if there's no such process.
+fn open_detail(
+    pid: Pid,
+    tab: DetailTab,
+    local_sys: &mut System,
+    shared_state: &Mutex<SharedState>,
+) -> Option<DetailView> {
+    let lines = details_for(pid, local_sys, shared_state)?;
+    let state = shared_state.lock().ok()?;
+    let name = match local_sys.process(pid) {
+        Some(proc) if state.replay.is_none() => proc.name().to_string(),
+        _ => state.processes.iter().find(|p| p.pid == pid).map_or(String::new(), |p| p.name.clone()),
+    };
+    Some(DetailView::new(pid, name, lines, tab, state.replay.is_none()))
+}
+
+/// Starts the live process and network monitor threads.
+fn spawn_collectors(shared_state: &Arc<Mutex<SharedState>>, recorder: Option<record::Recorder>, config: &Config) {
+    let state_for_process = Arc::clone(shared_state);
+    let state_for_network = Arc::clone(shared_state);
+    let interval = config.interval();
+    let interface_filter = config.interfaces.clone();
 
     // Thread 1: Process monitoring 
     thread::spawn(move || {
-        let mut sys = System::new_all();
-        sys.refresh_all();
-        let cpu_model = sys
-            .cpus()
-            .first()
-            .map_or("Unknown".to_string(), |cpu| cpu.brand().to_string());
-
-        let mut last_proc_read_total: u64 = 0;
-        let mut last_proc_write_total: u64 = 0;
-        let mut last_tick = Instant::now();
+        let mut collector = ProcessCollector::new();
+        let mut recorder = recorder;
 
         loop {
-            let is_paused = if let Ok(state) = state_for_process.lock() {
-                state.paused
+            let (is_paused, want_smaps, want_priorities, want_fd_counts) = if let Ok(state) = state_for_process.lock() {
+                (state.paused, state.want_smaps, state.want_priorities, state.want_fd_counts)
             } else {
-                false
+                (false, false, false, false)
             };
 
             if !is_paused {
-                sys.refresh_all();
-                
-                let now = Instant::now();
-                let dt = now.duration_since(last_tick).as_secs_f64().max(1e-9);
-                let proc_read_total: u64 = sys
-                    .processes()
-                    .values()
-                    .map(|p| p.disk_usage().total_read_bytes)
-                    .sum();
-                let proc_write_total: u64 = sys
-                    .processes()
-                    .values()
-                    .map(|p| p.disk_usage().total_written_bytes)
-                    .sum();
-
-                let disk_read_bps = (proc_read_total.saturating_sub(last_proc_read_total)) as f64 / dt;
-                let disk_write_bps = (proc_write_total.saturating_sub(last_proc_write_total)) as f64 / dt;
-
-                last_proc_read_total = proc_read_total;
-                last_proc_write_total = proc_write_total;
-
-                let processes: Vec<ProcessInfo> = sys
-                    .processes()
-                    .values()
-                    .map(|p| ProcessInfo {
-                        name: p.name().to_string(),
-                        pid: p.pid(),
-                        cpu_usage: p.cpu_usage(),
-                        memory: p.memory(),
-                        status: format!("{:?}", p.status()),
-                        run_time: p.run_time(),
-                    })
-                    .collect();
-
+                collector.set_pss_budget(want_smaps.then_some(collector::PSS_BUDGET));
+                collector.set_priorities(want_priorities);
+                collector.set_fd_counts(want_fd_counts);
+                let sample = collector.collect();
+                if let Some(rec) = recorder.as_mut() {
+                    let network = state_for_process
+                        .lock()
+                        .map(|state| state.network_data.clone())
+                        .unwrap_or_default();
+                    if let Err(e) = rec.append(&sample, &network) {
+                        recorder = None;
+                        if let Ok(mut state) = state_for_process.lock() {
+                            state.recording = Some(format!("REC failed: {}", e));
+                        }
+                    }
+                }
                 if let Ok(mut state) = state_for_process.lock() {
-                    state.processes = processes;
-                    state.cpu_model = cpu_model.clone();
-                    state.total_cpu_usage = sys.global_cpu_info().cpu_usage();
-                    state.total_memory = sys.total_memory();
-                    state.used_memory = sys.used_memory();
-                    state.available_memory = sys.available_memory();
-                    state.total_swap = sys.total_swap();
-                    state.used_swap = sys.used_swap();
-                    state.disk_read_bps = disk_read_bps;
-                    state.disk_write_bps = disk_write_bps;
+                    sample.apply(&mut state);
                 }
-
-                last_tick = now;
             }
             
-            thread::sleep(Duration::from_millis(1000));
+            thread::sleep(interval);
         }
     });
 
     // Thread 2: Network monitoring
     thread::spawn(move || {
-        let mut networks = Networks::new_with_refreshed_list();
-        let mut last_net_totals: HashMap<String, (u64, u64)> = networks
-            .iter()
-            .map(|(name, data)| (name.to_string(), (data.total_received(), data.total_transmitted())))
-            .collect();
-        let mut last_tick = Instant::now();
+        let mut collector = NetworkCollector::new(interface_filter);
 
         loop {
             //Check if paused
-            let is_paused = if let Ok(state) = state_for_network.lock() {
-                state.paused
+            let (is_paused, show_all) = if let Ok(state) = state_for_network.lock() {
+                (state.paused, state.show_all_interfaces)
             } else {
-                false
+                (false, false)
             };
 
             if !is_paused {
-                networks.refresh();
-                
-                let now = Instant::now();
-                let dt = now.duration_since(last_tick).as_secs_f64().max(1e-9);
-
-                let mut net_rows: Vec<(String, String, String, String, String)> = Vec::new();
-                for (name, data) in networks.iter() {
-                    let name_lower = name.to_lowercase();
-                    let should_exclude = name_lower.contains("npcap")
-                        || name_lower.contains("nocap")
-                        || name_lower.starts_with("lo")
-                        || name_lower.starts_with("docker")
-                        || name_lower.starts_with("veth")
-                        || name_lower.starts_with("br-")
-                        || name_lower.starts_with("vir");
-                    
-                    if should_exclude {
-                        continue;
-                    }
-                    
-                    let (prev_rx, prev_tx) = last_net_totals
-                        .get(name)
-                        .cloned()
-                        .unwrap_or((data.total_received(), data.total_transmitted()));
-                    let rx = data.total_received();
-                    let tx = data.total_transmitted();
-                    let rx_bps = (rx.saturating_sub(prev_rx)) as f64 / dt;
-                    let tx_bps = (tx.saturating_sub(prev_tx)) as f64 / dt;
-
-                    net_rows.push((
-                        name.to_string(),
-                        bytes_to_human(rx),
-                        bytes_to_human(tx),
-                        bytes_per_sec_human(rx_bps),
-                        bytes_per_sec_human(tx_bps),
-                    ));
-
-                    last_net_totals.insert(name.to_string(), (rx, tx));
-                }
-
-                net_rows.sort_by(|a, b| b.0.cmp(&a.0));
-
+                let net_rows = collector.collect(show_all);
                 if let Ok(mut state) = state_for_network.lock() {
-                    state.network_data = net_rows;
+                    collector::apply_network(net_rows, &mut state);
                 }
-
-                last_tick = now;
             }
             
-            thread::sleep(Duration::from_millis(1000));
+            thread::sleep(interval);
         }
     });
+}
+
+/// `--snapshot`: sample once without starting the UI and write the export file.
+fn run_snapshot(path: &std::path::Path, config: &Config) -> std::io::Result<()> {
+    let mut process_collector = ProcessCollector::new();
+    if config.wants_smaps() {
+        process_collector.set_pss_budget(Some(Duration::MAX));
+    }
+    let mut network_collector = NetworkCollector::new(config.interfaces.clone());
+    // CPU usage and rates need two refreshes some time apart
+    thread::sleep(config.interval());
+
+    let mut state = SharedState::new(config.interval());
+    process_collector.collect().apply(&mut state);
+    collector::apply_network(network_collector.collect(false), &mut state);
+    export::write_snapshot(path, &state)?;
+    println!("Wrote {} processes to {}", state.processes.len(), path.display());
+    Ok(())
+}
+
+fn main() -> std::io::Result<()> {
+    let args = match cli::Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    let config = match Config::load(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    units::init(config.units);
+    if args.batch {
+        return match batch::run(args.iterations, &config, args.format.unwrap_or(batch::BatchFormat::Text)) {
+            // `| head` closing the pipe is a normal way to stop
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+            Ok(()) => Ok(()),
+        };
+    }
+    if let Some(path) = &args.snapshot {
+        if let Err(e) = run_snapshot(path, &config) {
+            eprintln!("error: could not write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let shared_state = Arc::new(Mutex::new(SharedState::new(config.interval())));
+    // Shown in the command panel once the UI is up
+    let mut startup_notes = Vec::new();
+
+    if let Some(path) = &args.replay {
+        match record::load(path) {
+            Ok(recording) => {
+                if let Some(warning) = recording.warning() {
+                    eprintln!("warning: {}", warning);
+                    startup_notes.push(format!("Warning: {}", warning));
+                }
+                record::spawn_replay(recording, Arc::clone(&shared_state));
+            }
+            Err(e) => {
+                eprintln!("error: could not replay {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        let recorder = match &args.record {
+            Some(path) => match record::Recorder::open(path) {
+                Ok(recorder) => {
+                    if let Ok(mut state) = shared_state.lock() {
+                        state.recording = Some(format!("REC {}", path.display()));
+                    }
+                    Some(recorder)
+                }
+                Err(e) => {
+                    eprintln!("error: could not record to {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            },
+            None => None,
+        };
+        spawn_collectors(&shared_state, recorder, &config);
+    }
+
+    if let Some(addr) = args.metrics_addr {
+        #[cfg(feature = "metrics")]
+        if let Err(e) = metrics::spawn(addr, args.metrics_top, Arc::clone(&shared_state)) {
+            eprintln!("error: could not serve metrics on {}: {}", addr, e);
+            std::process::exit(1);
+        }
+        #[cfg(not(feature = "metrics"))]
+        {
+            eprintln!("error: --metrics-addr {} needs a build with `--features metrics`", addr);
+            std::process::exit(2);
+        }
+    }
 
     // Main thread
     enable_raw_mode()?;
@@ -250,10 +855,39 @@ fn main() -> std::io::Result<()> {
     execute!(stdout, EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
-    let mut sort_by = SortBy::Cpu;
+    let mut sort_by = config.sort;
+    let mut sort_descending = sort_by.default_descending();
+    // Process table columns, changed at runtime with `col`
+    let mut columns = config.columns.0.clone();
     let mut command_input = String::new();
     let mut command_mode = false;
-    let mut command_output: Vec<String> = Vec::new();
+    let mut command_output: Vec<String> = startup_notes;
+    // Signal waiting for a y/N confirmation: (pid, signal, process name)
+    let mut pending_signal: Option<(Pid, Signal, String)> = None;
+    // Process picked with 'k' whose signal hasn't been chosen yet
+    let mut signal_picker: Option<(Pid, String)> = None;
+
+    // Process table selection, pinned to a PID rather than a row index
+    let mut table_state = TableState::default();
+    let mut selected_pid: Option<Pid> = None;
+    let mut visible_pids: Vec<Pid> = Vec::new();
+    let mut table_page: usize = 10;
+    let mut tree_mode = false;
+    // '1' switches the System panel between the aggregate CPU graph and a per-core grid
+    let mut per_core = config.per_core;
+    // `disks all` lists partitions under their device instead of folding them into its row
+    let mut show_partitions = false;
+    let mut collapsed: HashSet<Pid> = HashSet::new();
+
+    // Process search: '/' to type, 'n'/'N' to jump, 'f' toggles filtering vs highlighting
+    let mut search_mode = false;
+    let mut search = Search::new("");
+    let mut search_filter = true;
+    let mut match_pids: HashSet<Pid> = HashSet::new();
+
+    // Detail pane for one process, opened with 'p <PID>' or Enter
+    let mut detail: Option<DetailView> = None;
+    let mut last_thread_refresh = Instant::now();
 
     //Store a local copy of system state for process detail lookups
     let mut local_sys = System::new_all();
@@ -264,15 +898,28 @@ fn main() -> std::io::Result<()> {
     let nvml = Nvml::init().ok();
 
     loop {
-        if last_sys_refresh.elapsed() > Duration::from_secs(2) {
+        if last_sys_refresh.elapsed() > config.detail_interval() {
             local_sys.refresh_all();
             last_sys_refresh = Instant::now();
+            // An exited process keeps its last details on screen
+            if let Some(view) = detail.as_mut().filter(|view| view.is_live()) {
+                if let Some(lines) = process_details(&local_sys, view.pid) {
+                    view.info_lines = lines;
+                }
+                view.refresh_tab();
+            }
+        }
+        if let Some(view) = detail.as_mut().filter(|view| view.is_live()) {
+            if last_thread_refresh.elapsed() >= config.interval() {
+                view.threads.refresh();
+                last_thread_refresh = Instant::now();
+            }
         }
 
-        let ui_update_interval = if command_mode {
+        let ui_update_interval = if command_mode || search_mode {
             Duration::from_millis(16)
         } else {
-            Duration::from_millis(100) 
+            config.ui_interval()
         };
 
         let should_update_ui = last_ui_update.elapsed() >= ui_update_interval;
@@ -287,8 +934,64 @@ fn main() -> std::io::Result<()> {
                 if key.kind != KeyEventKind::Press {
                     continue;
                 }
+                let replaying = shared_state.lock().map(|s| s.replay.is_some()).unwrap_or(false);
                 
-                if command_mode {
+                if let Some((pid, name)) = signal_picker.take() {
+                    let signal = match key.code {
+                        KeyCode::Char('t') => Some(Signal::Term),
+                        KeyCode::Char('k') => Some(Signal::Kill),
+                        KeyCode::Char('h') => Some(Signal::Hangup),
+                        KeyCode::Char('s') => Some(Signal::Stop),
+                        KeyCode::Char('c') => Some(Signal::Continue),
+                        _ => None,
+                    };
+                    if let Some(signal) = signal {
+                        pending_signal = Some((pid, signal, name));
+                    }
+                } else if let Some((pid, signal, name)) = pending_signal.take() {
+                    command_output.clear();
+                    if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                        match proc_control::send_signal(pid, signal) {
+                            Ok(()) => command_output.push(format!(
+                                "Sent {} to PID {} ({})",
+                                proc_control::signal_name(signal),
+                                pid,
+                                name
+                            )),
+                            Err(e) => command_output.push(format!(
+                                "Failed to send {} to PID {}: {}",
+                                proc_control::signal_name(signal),
+                                pid,
+                                proc_control::describe_os_error(&e)
+                            )),
+                        }
+                    } else {
+                        command_output.push(format!(
+                            "Cancelled {} for PID {}",
+                            proc_control::signal_name(signal),
+                            pid
+                        ));
+                    }
+                } else if search_mode {
+                    match key.code {
+                        KeyCode::Char(c) => {
+                            let mut pattern = search.pattern.clone();
+                            pattern.push(c);
+                            search = Search::new(&pattern);
+                        }
+                        KeyCode::Backspace => {
+                            let mut pattern = search.pattern.clone();
+                            pattern.pop();
+                            search = Search::new(&pattern);
+                        }
+                        KeyCode::Enter => search_mode = false,
+                        KeyCode::Esc => {
+                            search = Search::new("");
+                            search_mode = false;
+                        }
+                        _ => {}
+                    }
+                } else if command_mode {
                     match key.code {
                         KeyCode::Char(c) => {
                             command_input.push(c);
@@ -304,33 +1007,201 @@ fn main() -> std::io::Result<()> {
                                 let pid_str = cmd[2..].trim();
                                 if let Ok(pid_num) = pid_str.parse::<usize>() {
                                     let pid = Pid::from(pid_num);
-                                    local_sys.refresh_all();
-                                    if let Some(proc) = local_sys.process(pid) {
-                                        command_output.push(format!("Process Details for PID {}:", pid_num));
-                                        command_output.push(format!("  Name: {}", proc.name()));
-                                        command_output.push(format!("  Status: {:?}", proc.status()));
-                                        command_output.push(format!("  CPU Usage: {:.2}%", proc.cpu_usage()));
-                                        command_output.push(format!("  Memory: {}", bytes_to_human(proc.memory())));
-                                        command_output.push(format!("  Virtual Memory: {}", bytes_to_human(proc.virtual_memory())));
-                                        command_output.push(format!("  Runtime: {} seconds", proc.run_time()));
-                                        command_output.push(format!("  Disk Read: {}", bytes_to_human(proc.disk_usage().total_read_bytes)));
-                                        command_output.push(format!("  Disk Write: {}", bytes_to_human(proc.disk_usage().total_written_bytes)));
-                                        if let Some(cwd) = proc.cwd() {
-                                            command_output.push(format!("  CWD: {}", cwd.display()));
-                                        }
-                                        if let Some(exe) = proc.exe() {
-                                            command_output.push(format!("  Executable: {}", exe.display()));
-                                        }
+                                    if let Some(view) = open_detail(pid, DetailTab::Overview, &mut local_sys, &shared_state) {
+                                        detail = Some(view);
+                                        command_output.push(format!("Showing details for PID {} (Esc to close)", pid));
                                         last_sys_refresh = Instant::now();
+                                        last_thread_refresh = Instant::now();
                                     } else {
                                         command_output.push(format!("Process with PID {} not found", pid_num));
                                     }
                                 } else {
                                     command_output.push("Invalid PID format. Usage: p <PID>".to_string());
                                 }
+                            } else if let Some(rest) = cmd.strip_prefix("kill ") {
+                                let mut args = rest.split_whitespace();
+                                let pid_arg = args.next().and_then(|s| s.parse::<usize>().ok());
+                                // Without a signal the picker asks for one
+                                let signal_arg = args.next().map(proc_control::parse_signal);
+                                match (pid_arg, signal_arg) {
+                                    _ if replaying => {
+                                        command_output.push("Signals are disabled while replaying a recording".to_string());
+                                    }
+                                    (Some(pid_num), None | Some(Some(_))) => {
+                                        let pid = Pid::from(pid_num);
+                                        local_sys.refresh_process(pid);
+                                        match (local_sys.process(pid), signal_arg.flatten()) {
+                                            (Some(proc), Some(signal)) => {
+                                                pending_signal = Some((pid, signal, proc.name().to_string()))
+                                            }
+                                            (Some(proc), None) => signal_picker = Some((pid, proc.name().to_string())),
+                                            (None, _) => {
+                                                command_output.push(format!("Process with PID {} not found", pid_num))
+                                            }
+                                        }
+                                    }
+                                    (None, _) => {
+                                        command_output.push("Invalid PID format. Usage: kill <PID> [SIGNAL]".to_string());
+                                    }
+                                    (_, Some(None)) => {
+                                        command_output.push("Unknown signal. Use TERM, KILL, HUP, STOP, CONT, INT, QUIT, USR1 or USR2".to_string());
+                                    }
+                                }
+                            } else if let Some(path) = cmd.strip_prefix("export ") {
+                                let path = std::path::Path::new(path.trim());
+                                let result = match shared_state.lock() {
+                                    Ok(state) => export::write_snapshot(path, &state).map(|_| state.processes.len()),
+                                    Err(_) => Err(std::io::Error::other("state lock poisoned")),
+                                };
+                                match result {
+                                    Ok(count) => command_output.push(format!("Exported {} processes to {}", count, path.display())),
+                                    Err(e) => command_output.push(format!("Export to {} failed: {}", path.display(), e)),
+                                }
+                            } else if cmd == "ifaces" || cmd.starts_with("ifaces ") {
+                                let show_all = match cmd.strip_prefix("ifaces").map(str::trim) {
+                                    Some("all") => Some(true),
+                                    Some("filtered") => Some(false),
+                                    Some("") => shared_state.lock().ok().map(|s| !s.show_all_interfaces),
+                                    _ => None,
+                                };
+                                match show_all {
+                                    Some(_) if replaying => {
+                                        command_output.push("The interface list is fixed while replaying a recording".to_string());
+                                    }
+                                    Some(show_all) => {
+                                        if let Ok(mut state) = shared_state.lock() {
+                                            state.show_all_interfaces = show_all;
+                                        }
+                                        command_output.push(if show_all {
+                                            "Listing all interfaces, bridge ports under their bridge (next sample)".to_string()
+                                        } else {
+                                            "Listing interfaces allowed by the include/exclude patterns (next sample)".to_string()
+                                        });
+                                    }
+                                    None => command_output.push("Usage: ifaces [all|filtered]".to_string()),
+                                }
+                            } else if cmd == "disks" || cmd.starts_with("disks ") {
+                                let show = match cmd.strip_prefix("disks").map(str::trim) {
+                                    Some("all") => Some(true),
+                                    Some("folded") => Some(false),
+                                    Some("") => Some(!show_partitions),
+                                    _ => None,
+                                };
+                                match show {
+                                    Some(show) => {
+                                        show_partitions = show;
+                                        command_output.push(if show {
+                                            "Listing partitions under their device".to_string()
+                                        } else {
+                                            "Folding partitions into their device's row".to_string()
+                                        });
+                                    }
+                                    None => command_output.push("Usage: disks [all|folded]".to_string()),
+                                }
+                            } else if let Some(rest) = cmd.strip_prefix("nice ") {
+                                let mut args = rest.split_whitespace();
+                                let pid_arg = args.next().and_then(proc_control::parse_pid);
+                                let nice_arg = args.next().and_then(|s| s.parse::<i32>().ok());
+                                match (pid_arg, nice_arg) {
+                                    _ if replaying => {
+                                        command_output.push("Priorities can't be changed while replaying a recording".to_string());
+                                    }
+                                    (Some(pid), Some(nice)) if (-20..=19).contains(&nice) => {
+                                        command_output.push(renice(pid, nice));
+                                    }
+                                    _ => command_output.push("Usage: nice <PID> <-20..19>".to_string()),
+                                }
+                            } else if let Some(rest) = cmd.strip_prefix("ionice ") {
+                                let mut args = rest.split_whitespace();
+                                let pid_arg = args.next().and_then(proc_control::parse_pid);
+                                let class_arg = args.next().and_then(proc_control::IoClass::parse);
+                                let level_arg = match args.next() {
+                                    Some(s) => s.parse::<u8>().ok().filter(|l| *l <= 7),
+                                    None => Some(4),
+                                };
+                                match (pid_arg, class_arg, level_arg) {
+                                    _ if replaying => {
+                                        command_output.push("Priorities can't be changed while replaying a recording".to_string());
+                                    }
+                                    (Some(pid), Some(class), Some(level)) => {
+                                        command_output.push(set_io_priority(pid, class, level));
+                                    }
+                                    _ => command_output.push(
+                                        "Usage: ionice <PID> <none|realtime|best-effort|idle> [0-7, default 4]".to_string(),
+                                    ),
+                                }
+                            } else if let Some(rest) = cmd.strip_prefix("taskset ") {
+                                let mut args = rest.split_whitespace();
+                                let pid_arg = args.next().and_then(proc_control::parse_pid);
+                                let cpus_arg = args.next().map(proc_control::parse_cpu_list);
+                                let total_cpus = shared_state.lock().map_or(0, |s| s.cores.len());
+                                match (pid_arg, cpus_arg) {
+                                    _ if replaying => {
+                                        command_output.push("CPU affinity is not recorded, taskset needs live data".to_string());
+                                    }
+                                    (Some(pid), None) => {
+                                        command_output.extend(affinity_report(pid, total_cpus));
+                                    }
+                                    (Some(pid), Some(Ok(cpus))) => {
+                                        match proc_control::set_cpu_affinity(pid, &cpus) {
+                                            Ok(changes) => {
+                                                command_output.push(format!(
+                                                    "Set CPU affinity of PID {} to {} ({})",
+                                                    pid,
+                                                    proc_control::format_cpu_list(&cpus),
+                                                    thread_summary(&changes)
+                                                ));
+                                                command_output.extend(affinity_report(pid, total_cpus));
+                                            }
+                                            Err(e) => command_output.push(format!(
+                                                "Failed to set CPU affinity of PID {}: {}",
+                                                pid,
+                                                proc_control::describe_os_error(&e)
+                                            )),
+                                        }
+                                    }
+                                    (Some(_), Some(Err(e))) => command_output.push(e),
+                                    (None, _) => command_output.push("Usage: taskset <PID> [CPU list like 0-3,6 or mask like 0xf]".to_string()),
+                                }
+                            } else if cmd == "col" || cmd.starts_with("col ") {
+                                match cmd.strip_prefix("col").map(str::trim) {
+                                    Some("") | None => {
+                                        let hidden: Vec<SortBy> =
+                                            SortBy::ALL.iter().copied().filter(|c| !columns.contains(c)).collect();
+                                        let names = |cols: &[SortBy]| cols.iter().map(|c| c.label()).collect::<Vec<_>>().join(", ");
+                                        command_output.push(format!("Shown: {}", names(&columns)));
+                                        command_output.push(format!("Hidden: {}", names(&hidden)));
+                                        command_output.push("Usage: col <name> toggles a column, e.g. col nice".to_string());
+                                    }
+                                    Some(name) => match name.parse::<SortBy>() {
+                                        Ok(col) if columns.contains(&col) && columns.len() == 1 => {
+                                            command_output.push("The process table needs at least one column".to_string());
+                                        }
+                                        Ok(col) if columns.contains(&col) => {
+                                            columns.retain(|c| *c != col);
+                                            command_output.push(format!("Hid the {} column", col.label()));
+                                        }
+                                        Ok(col) => {
+                                            // Back at its usual place among the visible columns
+                                            let rank = |c: &SortBy| SortBy::ALL.iter().position(|a| a == c);
+                                            let at = columns.iter().position(|c| rank(c) > rank(&col)).unwrap_or(columns.len());
+                                            columns.insert(at, col);
+                                            command_output.push(format!("Showing the {} column", col.label()));
+                                        }
+                                        Err(e) => command_output.push(e),
+                                    },
+                                }
                             } else if cmd == "help" || cmd == "?" {
                                 command_output.push("Available commands:".to_string());
-                                command_output.push("  p <PID> - Show detailed process information".to_string());
+                                command_output.push("  p <PID> - Open the process detail view (overview, threads, files, maps, sockets)".to_string());
+                                command_output.push("  kill <PID> [SIGNAL] - Send a signal after confirmation, pick one if omitted".to_string());
+                                command_output.push("  export <path.csv|path.json> - Save all processes and system totals".to_string());
+                                command_output.push("  nice <PID> <VALUE> - Set the nice value (-20..19) of every thread".to_string());
+                                command_output.push("  ionice <PID> <CLASS> [LEVEL] - Set the I/O class (none, rt, be, idle) and level 0-7".to_string());
+                                command_output.push("  taskset <PID> [CPUS] - Show or set the CPU affinity of every thread (0-3,6 or 0xf)".to_string());
+                                command_output.push("  col [NAME] - Toggle a process table column, or list them".to_string());
+                                command_output.push("  ifaces [all|filtered] - Show every network interface or only the configured ones".to_string());
+                                command_output.push("  disks [all|folded] - List partitions under their disk or fold them into its row".to_string());
                                 command_output.push("  help or ? - Show this help message".to_string());
                                 command_output.push("  Press ESC to exit command mode".to_string());
                             } else if !cmd.is_empty() {
@@ -347,16 +1218,183 @@ fn main() -> std::io::Result<()> {
                         _ => {}
                     }
                 } else {
-                    // Normal mode input handling
+                    // Normal mode input handling. The process keys act on the process whose
+                    // details are open rather than the table row hidden behind them
+                    let target_pid = detail.as_ref().map(|view| view.pid).or(selected_pid);
                     match key.code {
                         KeyCode::Char(':') => {
                             command_mode = true;
                             command_input.clear();
                         }
+                        KeyCode::Char('/') => {
+                            search_mode = true;
+                            search = Search::new("");
+                        }
+                        KeyCode::Esc if detail.is_some() => detail = None,
+                        KeyCode::Tab | KeyCode::BackTab | KeyCode::Left | KeyCode::Right if detail.is_some() => {
+                            if let Some(view) = detail.as_mut() {
+                                view.switch_tab(matches!(key.code, KeyCode::Tab | KeyCode::Right));
+                            }
+                        }
+                        KeyCode::Esc if !search.is_empty() => search = Search::new(""),
+                        KeyCode::Char('f') => search_filter = !search_filter,
+                        KeyCode::Char('n') | KeyCode::Char('N') if !match_pids.is_empty() && !visible_pids.is_empty() => {
+                            let len = visible_pids.len();
+                            let current = selected_pid
+                                .and_then(|pid| visible_pids.iter().position(|p| *p == pid))
+                                .unwrap_or(0);
+                            let step = if key.code == KeyCode::Char('n') { 1 } else { len - 1 };
+                            let next = (1..=len)
+                                .map(|i| (current + i * step) % len)
+                                .find(|i| match_pids.contains(&visible_pids[*i]));
+                            if let Some(i) = next {
+                                selected_pid = Some(visible_pids[i]);
+                            }
+                        }
+                        KeyCode::Char('k') if replaying => {
+                            command_output.clear();
+                            command_output.push("Signals are disabled while replaying a recording".to_string());
+                        }
+                        KeyCode::Char('k') => {
+                            if let Some(pid) = target_pid {
+                                let name = shared_state
+                                    .lock()
+                                    .ok()
+                                    .and_then(|state| {
+                                        state.processes.iter().find(|p| p.pid == pid).map(|p| p.name.clone())
+                                    })
+                                    .unwrap_or_default();
+                                signal_picker = Some((pid, name));
+                            } else {
+                                command_mode = true;
+                                command_input = "kill ".to_string();
+                            }
+                        }
+                        KeyCode::F(7) | KeyCode::F(8) | KeyCode::Char('i') if replaying => {
+                            command_output.clear();
+                            command_output.push("Priorities can't be changed while replaying a recording".to_string());
+                        }
+                        // htop's keys: F7 raises the priority (nice - 1), F8 lowers it
+                        KeyCode::F(n @ (7 | 8)) => {
+                            if let Some(pid) = target_pid {
+                                command_output.clear();
+                                match proc_control::nice_of(pid) {
+                                    Some(nice) => {
+                                        let target = (nice + if n == 7 { -1 } else { 1 }).clamp(-20, 19);
+                                        command_output.push(renice(pid, target));
+                                    }
+                                    None => command_output.push(format!("Could not read the nice value of PID {}", pid)),
+                                }
+                            }
+                        }
+                        KeyCode::Char('i') => {
+                            command_mode = true;
+                            command_input = match target_pid {
+                                Some(pid) => format!("ionice {} ", pid),
+                                None => "ionice ".to_string(),
+                            };
+                        }
+                        KeyCode::Char('H') if replaying => {
+                            command_output.clear();
+                            command_output.push("Threads are not recorded, the thread view needs live data".to_string());
+                        }
+                        KeyCode::Char('H') => {
+                            if let Some(pid) = target_pid {
+                                command_output.clear();
+                                last_sys_refresh = Instant::now();
+                                last_thread_refresh = Instant::now();
+                                detail = open_detail(pid, DetailTab::Threads, &mut local_sys, &shared_state);
+                                if detail.is_none() {
+                                    command_output.push(format!("Process with PID {} not found", pid));
+                                }
+                            }
+                        }
+                        KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End
+                            if detail.is_some() =>
+                        {
+                            if let Some(view) = detail.as_mut() {
+                                let page = view.page();
+                                view.scroll(match key.code {
+                                    KeyCode::Up => -1,
+                                    KeyCode::Down => 1,
+                                    KeyCode::PageUp => -page,
+                                    KeyCode::PageDown => page,
+                                    KeyCode::Home => isize::MIN,
+                                    _ => isize::MAX,
+                                });
+                            }
+                        }
+                        KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End
+                            if !visible_pids.is_empty() =>
+                        {
+                            let last = visible_pids.len() - 1;
+                            let current = selected_pid
+                                .and_then(|pid| visible_pids.iter().position(|p| *p == pid))
+                                .unwrap_or(0);
+                            let next = match key.code {
+                                KeyCode::Up => current.saturating_sub(1),
+                                KeyCode::Down => (current + 1).min(last),
+                                KeyCode::PageUp => current.saturating_sub(table_page),
+                                KeyCode::PageDown => (current + table_page).min(last),
+                                KeyCode::Home => 0,
+                                _ => last,
+                            };
+                            selected_pid = Some(visible_pids[next]);
+                        }
+                        KeyCode::Enter => {
+                            if let Some(pid) = selected_pid {
+                                command_output.clear();
+                                last_sys_refresh = Instant::now();
+                                last_thread_refresh = Instant::now();
+                                detail = open_detail(pid, DetailTab::Overview, &mut local_sys, &shared_state);
+                                if detail.is_none() {
+                                    command_output.push(format!("Process with PID {} not found", pid));
+                                }
+                            }
+                        }
                         KeyCode::Char('q') => break,
-                        KeyCode::Char('c') => sort_by = SortBy::Cpu,
-                        KeyCode::Char('m') => sort_by = SortBy::Memory,
-                        KeyCode::Char('p') => sort_by = SortBy::Pid,
+                        KeyCode::Char(c @ ('[' | ']' | '+' | '=' | '-')) if replaying => {
+                            if let Ok(mut state) = shared_state.lock() {
+                                if let Some(replay) = state.replay.as_mut() {
+                                    match c {
+                                        '[' => replay.pending_step -= 1,
+                                        ']' => replay.pending_step += 1,
+                                        '-' => replay.slower(),
+                                        _ => replay.faster(),
+                                    }
+                                }
+                            }
+                        }
+                        KeyCode::Char(c @ ('c' | 'm' | 'p' | '<' | '>')) => {
+                            sort_by = match c {
+                                'c' => SortBy::Cpu,
+                                'm' => SortBy::Memory,
+                                'p' => SortBy::Pid,
+                                _ => sort_by.cycle(c == '>', &columns),
+                            };
+                            sort_descending = sort_by.default_descending();
+                        }
+                        KeyCode::Char('r') => sort_descending = !sort_descending,
+                        KeyCode::Char('t') => tree_mode = !tree_mode,
+                        KeyCode::Char('1') => per_core = !per_core,
+                        KeyCode::Left if tree_mode => {
+                            // Collapse the selected subtree, or from a leaf jump to its parent and collapse that
+                            if let (Some(pid), Ok(state)) = (selected_pid, shared_state.lock()) {
+                                let has_children = state.processes.iter().any(|p| p.parent == Some(pid));
+                                let parent = state.processes.iter().find(|p| p.pid == pid).and_then(|p| p.parent);
+                                if has_children {
+                                    collapsed.insert(pid);
+                                } else if let Some(parent) = parent.filter(|pp| visible_pids.contains(pp)) {
+                                    collapsed.insert(parent);
+                                    selected_pid = Some(parent);
+                                }
+                            }
+                        }
+                        KeyCode::Right if tree_mode => {
+                            if let Some(pid) = selected_pid {
+                                collapsed.remove(&pid);
+                            }
+                        }
                         KeyCode::Char(' ') | KeyCode::Char('s') => {
                             if let Ok(mut state) = shared_state.lock() {
                                 state.paused = !state.paused;
@@ -374,30 +1412,125 @@ fn main() -> std::io::Result<()> {
         }
 
         last_ui_update = Instant::now();
+        if let Ok(mut state) = shared_state.lock() {
+            state.want_smaps = columns.iter().chain([&sort_by]).any(|c| c.needs_smaps());
+            state.want_priorities = columns.iter().chain([&sort_by]).any(|c| c.needs_priorities());
+            state.want_fd_counts = columns.iter().chain([&sort_by]).any(|c| c.needs_fd_counts());
+        }
 
         //Draw ui
         terminal.draw(|f| {
             let size = f.area();
             let state = shared_state.lock().unwrap();
 
-            let outer = Layout::default()
+            // Hidden panels get an empty area, so drawing into them is a no-op. The command
+            // line still shows up while a prompt is active.
+            let panels = &config.panels;
+            let show_command = panels.command
+                || command_mode
+                || search_mode
+                || pending_signal.is_some()
+                || signal_picker.is_some();
+            let show_bottom = panels.memory || panels.network;
+            // The command line grows to fit longer output like `help`
+            let output_rows = command_output.len().clamp(5, 14);
+            // The grid never takes more than a third of the screen, cores past that are summarized
+            let core_rows = if per_core {
+                cores::grid_height(state.cores.len(), size.width.saturating_sub(2)).min(size.height / 3)
+            } else {
+                0
+            };
+            // Bottom stats: RAM n Network
+            let bottom_split = match (panels.memory, panels.network) {
+                (true, true) => [Constraint::Percentage(50), Constraint::Percentage(50)],
+                (true, false) => [Constraint::Percentage(100), Constraint::Length(0)],
+                _ => [Constraint::Length(0), Constraint::Percentage(100)],
+            };
+            let ram_split_constraints = [Constraint::Percentage(55), Constraint::Percentage(45)];
+            // How the /proc/meminfo breakdown wraps decides the height of the Memory panel, so
+            // its width is worked out ahead of the layout
+            let memory_breakdown = match &state.meminfo {
+                Some(info) if panels.memory => {
+                    let probe = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(bottom_split)
+                        .split(Rect::new(0, 0, size.width, 3))[0];
+                    let text = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(ram_split_constraints)
+                        .split(Block::default().borders(Borders::ALL).inner(probe))[0];
+                    meminfo::breakdown_lines(info, text.width)
+                }
+                _ => Vec::new(),
+            };
+            // RAM, Available, Swap and Disk I/O, spaced out by blank lines without a breakdown
+            let memory_rows = if state.meminfo.is_some() { 4 + memory_breakdown.len() } else { 6 };
+            let bottom_rows = (memory_rows as u16 + 2).max(12);
+            let slots = [
+                (panels.system, Constraint::Length(9 + core_rows)),
+                (panels.processes, Constraint::Min(8)),
+                (show_bottom, if panels.processes { Constraint::Length(bottom_rows) } else { Constraint::Min(bottom_rows) }),
+                (panels.disks, Constraint::Length(diskstats::panel_height(&state.disks, show_partitions))),
+                (show_command, Constraint::Length(output_rows as u16 + 3)),
+            ];
+            let shown = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Length(5),
-                    Constraint::Min(8),
-                    Constraint::Length(10),
-                    Constraint::Length(8),
-                ])
+                .constraints(slots.iter().filter(|(visible, _)| *visible).map(|(_, c)| *c))
                 .split(size);
+            let mut shown = shown.iter().copied();
+            let outer: Vec<Rect> = slots
+                .iter()
+                .map(|(visible, _)| if *visible { shown.next().unwrap_or_default() } else { Rect::default() })
+                .collect();
 
             //System info
-            let sort_label = match sort_by {
-                SortBy::Cpu => "CPU",
-                SortBy::Memory => "Memory",
-                SortBy::Pid => "PID",
-            };
-            let pause_status = if state.paused { " [PAUSED]" } else { "" };
+            let sort_arrow = if sort_descending { "▼" } else { "▲" };
+            let sort_label = format!("{} {}", sort_by.label(), sort_arrow);
+            let mut pause_status = if state.paused { " [PAUSED]" } else { "" }.to_string();
+            if let Some(replay) = &state.replay {
+                pause_status.push_str(&format!(
+                    " [REPLAY {} {}/{} x{} | '['/']'=Step '-'/'+'=Speed]",
+                    export::format_timestamp(std::time::UNIX_EPOCH + Duration::from_millis(replay.t)),
+                    replay.position + 1,
+                    replay.len,
+                    replay.speed
+                ));
+            } else if let Some(recording) = &state.recording {
+                pause_status.push_str(&format!(" [{}]", recording));
+            }
+            let host = &state.host;
+            let tasks = TaskCounts::from_processes(&state.processes);
             let mut system_text = vec![
+                Line::from(Span::styled(
+                    format!(
+                        "{} | Up {} | Load: {:.2} {:.2} {:.2} | Kernel {} | Booted {}",
+                        host.hostname,
+                        format_uptime(host.uptime),
+                        host.load_average[0],
+                        host.load_average[1],
+                        host.load_average[2],
+                        host.kernel,
+                        export::format_timestamp(std::time::UNIX_EPOCH + Duration::from_secs(host.boot_time)),
+                    ),
+                    Style::default().fg(Color::White),
+                )),
+                Line::from(vec![
+                    Span::styled(
+                        format!(
+                            "Tasks: {} total, {} running, {} sleeping, {} stopped, ",
+                            tasks.total, tasks.running, tasks.sleeping, tasks.stopped
+                        ),
+                        Style::default().fg(Color::White),
+                    ),
+                    Span::styled(
+                        format!("{} zombie", tasks.zombie),
+                        if tasks.zombie > 0 {
+                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::default().fg(Color::White)
+                        },
+                    ),
+                ]),
                 Line::from(Span::styled(
                     format!("CPU Model: {}", state.cpu_model),
                     Style::default().fg(Color::Green),
@@ -411,14 +1544,14 @@ fn main() -> std::io::Result<()> {
                     },
                 )),
                 Line::from(Span::styled(
-                    format!("Sort: {} | 'c'=CPU 'm'=Memory 'p'=PID | Space/s=Pause | ':'=Cmd", sort_label),
+                    format!("Sort: {} | 'c'=CPU 'm'=Memory 'p'=PID '<'/'>'=Column 'r'=Reverse | 't'=Tree | '1'=Cores | '/'=Search | Space/s=Pause | ':'=Cmd", sort_label),
                     Style::default().fg(Color::Cyan),
                 )),
                 Line::from(Span::styled(
                     format!(
-                        "RAM: {}/{} ({:.2}%)",
-                        state.used_memory / 1024, // Convert KB to MB
-                        state.total_memory / 1024,
+                        "RAM: {} / {} ({:.2}%)",
+                        units::bytes(state.used_memory),
+                        units::bytes(state.total_memory),
                         (state.used_memory as f64 / state.total_memory as f64) * 100.0
                     ),
                     Style::default().fg(Color::Blue),
@@ -435,9 +1568,9 @@ fn main() -> std::io::Result<()> {
                         if let Ok(memory) = device.memory_info() {
                             system_text.push(Line::from(Span::styled(
                                 format!(
-                                    "GPU Memory: {}/{} MB ({:.2}%)",
-                                    memory.used / 1024 / 1024, // Convert bytes to MB
-                                    memory.total / 1024 / 1024,
+                                    "GPU Memory: {} / {} ({:.2}%)",
+                                    units::bytes(memory.used),
+                                    units::bytes(memory.total),
                                     (memory.used as f64 / memory.total as f64) * 100.0
                                 ),
                                 Style::default().fg(Color::Magenta),
@@ -461,89 +1594,198 @@ fn main() -> std::io::Result<()> {
                 .title("System")
                 .borders(Borders::ALL)
                 .style(Style::default().fg(Color::White));
+            let system_inner = system_block.inner(outer[0]);
+            f.render_widget(system_block, outer[0]);
+            let system_rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(7), Constraint::Length(core_rows)])
+                .split(system_inner);
+            if per_core {
+                cores::draw(f, system_rows[1], &state.cores, &config.thresholds);
+            }
+            let system_split = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .split(system_rows[0]);
+            f.render_widget(ratatui::widgets::Paragraph::new(system_text), system_split[0]);
+
+            let cpu_spark_width = system_split[1].width.saturating_sub(1) as usize;
+            let cpu_history = state.history.cpu.last_scaled(cpu_spark_width, 1.0);
             f.render_widget(
-                ratatui::widgets::Paragraph::new(system_text).block(system_block),
-                outer[0],
+                Sparkline::default()
+                    .block(Block::default().title(format!(
+                        "CPU % (max {:.0}%, last {})",
+                        state.history.cpu.max(),
+                        history_span(state.history.cpu.seconds(state.history.cpu.len().min(cpu_spark_width)))
+                    )).borders(Borders::LEFT))
+                    .data(&cpu_history)
+                    .max(100)
+                    .style(Style::default().fg(Color::Yellow)),
+                system_split[1],
             );
 
             //Processes table
             let mut procs = state.processes.clone();
-            
-            match sort_by {
-                SortBy::Cpu => {
-                    procs.sort_by(|a, b| {
-                        b.cpu_usage
-                            .partial_cmp(&a.cpu_usage)
-                            .unwrap_or(std::cmp::Ordering::Equal)
-                    });
-                }
-                SortBy::Memory => {
-                    procs.sort_by(|a, b| {
-                        b.memory
-                            .partial_cmp(&a.memory)
-                            .unwrap_or(std::cmp::Ordering::Equal)
-                    });
-                }
-                SortBy::Pid => {
-                    procs.sort_by(|a, b| a.pid.cmp(&b.pid));
-                }
+            let total_procs = procs.len();
+            match_pids = procs
+                .iter()
+                .filter(|p| search.matches(p))
+                .map(|p| p.pid)
+                .collect();
+            if search_filter && !search.is_empty() && search.error.is_none() {
+                procs.retain(|p| match_pids.contains(&p.pid));
+            }
+            sort_processes(&mut procs, sort_by, sort_descending);
+            let mut procs: Vec<TreeRow> = if tree_mode {
+                tree::build_tree(&procs, &collapsed)
+            } else {
+                procs.into_iter().map(TreeRow::flat).collect()
+            };
+            if config.max_processes > 0 {
+                procs.truncate(config.max_processes);
             }
 
+            // Keep the selection on the same PID across refreshes and re-sorts. If that
+            // process went away, stay at the same row index instead.
+            visible_pids = procs.iter().map(|row| row.info.pid).collect();
+            let selected_index = if procs.is_empty() {
+                None
+            } else {
+                let index = selected_pid
+                    .and_then(|pid| visible_pids.iter().position(|p| *p == pid))
+                    .unwrap_or_else(|| table_state.selected().unwrap_or(0).min(procs.len() - 1));
+                Some(index)
+            };
+            selected_pid = selected_index.map(|i| visible_pids[i]);
+            table_state.select(selected_index);
+            // Rows visible in the table: borders and header take 4 lines
+            table_page = (outer[1].height as usize).saturating_sub(4).max(1);
+
             let total_mem = state.total_memory;
+            let thresholds = &config.thresholds;
             let rows: Vec<Row> = procs
                 .iter()
-                .take(30)
-                .map(|p| {
+                .map(|row| {
+                    let p = &row.info;
                     let mem_bytes = p.memory;
                     let mem_pct = (mem_bytes as f64 / total_mem as f64) * 100.0;
-                    let row_content = vec![
-                        p.name.clone(),
-                        p.pid.to_string(),
-                        format!("{:.2}%", p.cpu_usage),
-                        format!("{} ({:.1}%)", bytes_to_human(mem_bytes), mem_pct),
-                        p.status.clone(),
-                        format!("{}", p.run_time),
-                    ];
-
-                    let style = if p.cpu_usage > 80.0 {
+
+                    // Highlight the matched part of the name
+                    let mut name_spans = Vec::new();
+                    if tree_mode {
+                        name_spans.push(Span::raw(row.name_prefix()));
+                    }
+                    let parts = search.find(&p.name).and_then(|(start, end)| {
+                        Some((p.name.get(..start)?, p.name.get(start..end)?, p.name.get(end..)?))
+                    });
+                    match parts {
+                        Some((before, matched, after)) => {
+                            name_spans.push(Span::raw(before.to_string()));
+                            name_spans.push(Span::styled(
+                                matched.to_string(),
+                                Style::default().fg(Color::Black).bg(Color::Yellow),
+                            ));
+                            name_spans.push(Span::raw(after.to_string()));
+                        }
+                        None => name_spans.push(Span::raw(p.name.clone())),
+                    }
+                    if tree_mode {
+                        name_spans.push(Span::raw(row.name_suffix()));
+                    }
+
+                    let mut name_spans = Some(name_spans);
+                    let row_content: Vec<Cell> = columns
+                        .iter()
+                        .map(|col| match col {
+                            SortBy::Name => Cell::from(Line::from(name_spans.take().unwrap_or_default())),
+                            SortBy::Pid => Cell::from(p.pid.to_string()),
+                            SortBy::User => Cell::from(p.user.clone()),
+                            SortBy::Cpu => Cell::from(format!("{:.2}%", p.cpu_usage)),
+                            SortBy::Memory => Cell::from(format!("{} ({:.1}%)", units::bytes(mem_bytes), mem_pct)),
+                            SortBy::Threads => Cell::from(p.threads.to_string()),
+                            SortBy::DiskIo => Cell::from(units::rate(p.disk_read_bps + p.disk_write_bps)),
+                            SortBy::Status => Cell::from(p.status.clone()),
+                            SortBy::Runtime => Cell::from(format!("{}", p.run_time)),
+                            SortBy::Nice => Cell::from(p.nice.map_or("-".to_string(), |n| n.to_string())),
+                            SortBy::IoPriority => Cell::from(p.io_priority.clone().unwrap_or_else(|| "-".to_string())),
+                            SortBy::Parent => Cell::from(p.parent.map_or("-".to_string(), |pid| pid.to_string())),
+                            SortBy::StartTime => Cell::from(export::format_timestamp(
+                                std::time::UNIX_EPOCH + Duration::from_secs(p.start_time),
+                            )),
+                            SortBy::Command => Cell::from(if p.cmd.is_empty() { format!("[{}]", p.name) } else { p.cmd.clone() }),
+                            SortBy::Fds => Cell::from(p.fd_count.map_or("-".to_string(), |n| n.to_string())),
+                            SortBy::Pss => Cell::from(p.pss.map_or("-".to_string(), units::bytes)),
+                            SortBy::Uss => Cell::from(p.uss.map_or("-".to_string(), units::bytes)),
+                        })
+                        .collect();
+
+                    let style = if p.cpu_usage > thresholds.cpu_crit {
                         Style::default().fg(Color::Red)
-                    } else if p.cpu_usage > 50.0 {
+                    } else if p.cpu_usage > thresholds.cpu_warn {
                         Style::default().fg(Color::Yellow)
-                    } else if mem_pct > 20.0 {
+                    } else if mem_pct > thresholds.process_memory {
                         Style::default().fg(Color::Magenta)
                     } else {
                         Style::default().fg(Color::White)
                     };
+                    let style = if !search_filter && match_pids.contains(&p.pid) {
+                        style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+                    } else {
+                        style
+                    };
 
                     Row::new(row_content).style(style)
                 })
                 .collect();
 
-            let table = Table::new(
-                rows,
-                [
-                    Constraint::Percentage(25),
-                    Constraint::Percentage(12),
-                    Constraint::Percentage(12),
-                    Constraint::Percentage(23),
-                    Constraint::Percentage(13),
-                    Constraint::Percentage(15),
-                ],
-            )
+            let table = Table::new(rows, columns.iter().map(|col| Constraint::Fill(col.width())))
             .header(
-                Row::new(vec!["Name", "PID", "CPU %", "Memory", "Status", "Runtime"])
+                Row::new(columns.iter().map(|col| {
+                    if *col == sort_by {
+                        format!("{} {}", col.label(), sort_arrow)
+                    } else {
+                        col.label().to_string()
+                    }
+                }))
                     .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
                     .bottom_margin(1),
             )
-            .block(Block::default().title("Top Processes").borders(Borders::ALL))
-            .style(Style::default().fg(Color::White));
+            .block(Block::default().title(format!(
+                "{} [{}/{}]{}",
+                if tree_mode { "Process Tree (←/→ collapse/expand)" } else { "Top Processes" },
+                selected_index.map_or(0, |i| i + 1),
+                procs.len(),
+                if let Some(err) = &search.error {
+                    format!(" /{}: {}", search.pattern, err)
+                } else if !search.is_empty() {
+                    format!(
+                        " /{}: {} of {}{}",
+                        search.pattern,
+                        match_pids.len(),
+                        total_procs,
+                        if search_filter { "" } else { " (highlight, 'f' to filter)" }
+                    )
+                } else {
+                    String::new()
+                }
+            )).borders(Borders::ALL))
+            .style(Style::default().fg(Color::White))
+            .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
 
-            f.render_widget(table, outer[1]);
+            f.render_stateful_widget(table, outer[1], &mut table_state);
+
+            let mut scrollbar_state = ScrollbarState::new(procs.len())
+                .position(selected_index.unwrap_or(0));
+            f.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight),
+                outer[1].inner(ratatui::layout::Margin { vertical: 1, horizontal: 0 }),
+                &mut scrollbar_state,
+            );
 
-            // Bottom stats: RAM n Network
             let bottom = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .constraints(bottom_split)
                 .split(outer[2]);
 
             //RAM  panel
@@ -564,75 +1806,135 @@ fn main() -> std::io::Result<()> {
                 0.0
             };
 
+            let ram_block = Block::default().title("Memory").borders(Borders::ALL);
+            let ram_inner = ram_block.inner(bottom[0]);
+            f.render_widget(ram_block, bottom[0]);
+            let ram_split = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(ram_split_constraints)
+                .split(ram_inner);
+            // The breakdown takes the rows the blank separators would
+            let separator = |lines: &mut Vec<Line>| {
+                if state.meminfo.is_none() {
+                    lines.push(Line::from(""));
+                }
+            };
+
             let mut ram_lines: Vec<Line> = vec![
                 Line::from(Span::styled(
                     format!("RAM: {} / {} ({:.1}%)", 
-                        bytes_to_human(used_mem),
-                        bytes_to_human(total_mem),
+                        units::bytes(used_mem),
+                        units::bytes(total_mem),
                         mem_percent
                     ),
-                    if mem_percent > 90.0 {
+                    if mem_percent > thresholds.ram_crit {
                         Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
-                    } else if mem_percent > 75.0 {
+                    } else if mem_percent > thresholds.ram_warn {
                         Style::default().fg(Color::Yellow)
                     } else {
                         Style::default().fg(Color::Green)
                     },
                 )),
                 Line::from(Span::styled(
-                    format!("Available: {}", bytes_to_human(available_mem)),
+                    format!("Available: {}", units::bytes(available_mem)),
                     Style::default().fg(Color::Cyan),
                 )),
             ];
-
             if total_swap > 0 {
-                ram_lines.push(Line::from(""));
+                separator(&mut ram_lines);
                 ram_lines.push(Line::from(Span::styled(
                     format!("Swap: {} / {} ({:.1}%)", 
-                        bytes_to_human(used_swap),
-                        bytes_to_human(total_swap),
+                        units::bytes(used_swap),
+                        units::bytes(total_swap),
                         swap_percent
                     ),
-                    if swap_percent > 75.0 {
+                    if swap_percent > thresholds.swap_crit {
                         Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
-                    } else if swap_percent > 50.0 {
+                    } else if swap_percent > thresholds.swap_warn {
                         Style::default().fg(Color::Yellow)
                     } else {
                         Style::default().fg(Color::Cyan)
                     },
                 )));
             } else {
-                ram_lines.push(Line::from(""));
+                separator(&mut ram_lines);
                 ram_lines.push(Line::from(Span::styled(
                     "Swap: Not configured",
                     Style::default().fg(Color::DarkGray),
                 )));
             }
 
-            ram_lines.push(Line::from(""));
+            separator(&mut ram_lines);
             ram_lines.push(Line::from(Span::styled(
                 format!("Disk I/O: ↓{} ↑{}", 
-                    bytes_per_sec_human(state.disk_read_bps),
-                    bytes_per_sec_human(state.disk_write_bps)
+                    units::rate(state.disk_read_bps),
+                    units::rate(state.disk_write_bps)
                 ),
                 Style::default().fg(Color::Magenta),
             )));
+            // "Used" alone hides how much of it is cache the kernel would give back
+            ram_lines.extend(memory_breakdown);
 
-            let ram_block = Block::default().title("Memory").borders(Borders::ALL);
+            f.render_widget(ratatui::widgets::Paragraph::new(ram_lines), ram_split[0]);
+
+            let ram_charts = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(ram_split[1]);
+            let ram_spark_width = ram_split[1].width.saturating_sub(1) as usize;
+            let ram_history = state.history.memory.last_scaled(ram_spark_width, 1.0);
             f.render_widget(
-                ratatui::widgets::Paragraph::new(ram_lines).block(ram_block),
-                bottom[0],
+                Sparkline::default()
+                    .block(Block::default().title("RAM %").borders(Borders::LEFT))
+                    .data(&ram_history)
+                    .max(100)
+                    .style(Style::default().fg(Color::Green)),
+                ram_charts[0],
+            );
+            // Reads and writes share one sparkline, scaled to the busier of the two
+            let disk_history: Vec<u64> = state
+                .history
+                .disk_read
+                .last_scaled(ram_spark_width, 1.0)
+                .into_iter()
+                .zip(state.history.disk_write.last_scaled(ram_spark_width, 1.0))
+                .map(|(r, w)| r + w)
+                .collect();
+            f.render_widget(
+                Sparkline::default()
+                    .block(Block::default().title(format!(
+                        "Disk I/O (peak {})",
+                        units::rate(disk_history.iter().copied().max().unwrap_or(0) as f64)
+                    )).borders(Borders::LEFT))
+                    .data(&disk_history)
+                    .style(Style::default().fg(Color::Magenta)),
+                ram_charts[1],
             );
 
             //Network panel
+            // Bridge ports are folded into their bridge's row, `ifaces all` lists them under it
+            let show_all_ifaces = state.show_all_interfaces;
             let mut net_table_rows: Vec<Row> = Vec::new();
-            for (name, rx_total, tx_total, rx_rate, tx_rate) in state.network_data.iter().take(6) {
+            for net in state
+                .network_data
+                .iter()
+                .filter(|n| show_all_ifaces || !collector::is_grouped_port(n, &state.network_data))
+                .take(config.max_interfaces)
+            {
+                let ports = state.network_data.iter().filter(|n| n.master.as_ref() == Some(&net.name)).count();
+                let name = if collector::is_grouped_port(net, &state.network_data) {
+                    format!(" └{}", net.name)
+                } else if ports > 0 && !show_all_ifaces {
+                    format!("{} (+{})", net.name, ports)
+                } else {
+                    net.name.clone()
+                };
                 net_table_rows.push(Row::new(vec![
-                    name.clone(),
-                    rx_rate.clone(),
-                    tx_rate.clone(),
-                    rx_total.clone(),
-                    tx_total.clone(),
+                    name,
+                    units::net_rate(net.rx_bps),
+                    units::net_rate(net.tx_bps),
+                    units::bytes(net.rx_total),
+                    units::bytes(net.tx_total),
                 ]));
             }
 
@@ -651,12 +1953,68 @@ fn main() -> std::io::Result<()> {
                     .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
                     .bottom_margin(1),
             )
-            .block(Block::default().title("Network").borders(Borders::ALL));
+            .block(Block::default().title(if show_all_ifaces { "Network (all, 'ifaces' to filter)" } else { "Network" }).borders(Borders::ALL));
+
+            let net_split = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+                .split(bottom[1]);
+            f.render_widget(net_table, net_split[0]);
 
-            f.render_widget(net_table, bottom[1]);
+            let rx_points = state.history.net_rx.points();
+            let tx_points = state.history.net_tx.points();
+            let net_peak = state.history.net_rx.max().max(state.history.net_tx.max()).max(1.0);
+            let net_span = state.history.net_rx.seconds(state.history.net_rx.len().max(2) - 1);
+            let net_chart = Chart::new(vec![
+                Dataset::default()
+                    .name("RX")
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(Color::Cyan))
+                    .data(&rx_points),
+                Dataset::default()
+                    .name("TX")
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(Color::Magenta))
+                    .data(&tx_points),
+            ])
+            .block(Block::default().title("Traffic").borders(Borders::ALL))
+            .x_axis(
+                Axis::default()
+                    .bounds([-net_span, 0.0])
+                    .labels(vec![
+                        Span::raw(format!("-{}", history_span(net_span))),
+                        Span::raw("now"),
+                    ])
+                    .style(Style::default().fg(Color::DarkGray)),
+            )
+            .y_axis(
+                Axis::default()
+                    .bounds([0.0, net_peak * 1.1])
+                    .labels(vec![Span::raw("0"), Span::raw(units::net_rate(net_peak))])
+                    .style(Style::default().fg(Color::DarkGray)),
+            );
+            f.render_widget(net_chart, net_split[1]);
+
+            diskstats::draw(f, outer[3], &state.disks, show_partitions, thresholds);
 
             // Command Line panel
-            let cmd_prompt = if command_mode {
+            let cmd_prompt = if let Some((pid, name)) = &signal_picker {
+                format!(
+                    "> Signal for PID {} ({}): [t]erm [k]ill [h]up [s]top [c]ont, any other key cancels",
+                    pid, name
+                )
+            } else if let Some((pid, signal, name)) = &pending_signal {
+                format!(
+                    "> Send {} to PID {} ({})? [y/N]",
+                    proc_control::signal_name(*signal),
+                    pid,
+                    name
+                )
+            } else if search_mode {
+                format!("/{}_", search.pattern)
+            } else if command_mode {
                 format!("> {}_", command_input)
             } else {
                 "> (Press ':' to enter command mode, 'p <PID>' for process details)".to_string()
@@ -665,7 +2023,9 @@ fn main() -> std::io::Result<()> {
             let mut cmd_lines = vec![
                 Line::from(Span::styled(
                     cmd_prompt,
-                    if command_mode {
+                    if pending_signal.is_some() || signal_picker.is_some() {
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                    } else if command_mode || search_mode {
                         Style::default().fg(Color::Green)
                     } else {
                         Style::default().fg(Color::DarkGray)
@@ -674,7 +2034,7 @@ fn main() -> std::io::Result<()> {
             ];
             
             //Show command output
-            for output_line in command_output.iter().rev().take(5).rev() {
+            for output_line in command_output.iter().rev().take(output_rows).rev() {
                 cmd_lines.push(Line::from(Span::styled(
                     output_line.clone(),
                     Style::default().fg(Color::Yellow),
@@ -687,8 +2047,18 @@ fn main() -> std::io::Result<()> {
                 .style(Style::default().fg(Color::White));
             f.render_widget(
                 ratatui::widgets::Paragraph::new(cmd_lines).block(cmd_block),
-                outer[3],
+                outer[4],
             );
+
+            // The detail view covers every panel but the command line
+            if let Some(view) = detail.as_mut() {
+                let area = outer[..4]
+                    .iter()
+                    .filter(|r| !r.is_empty())
+                    .fold(None, |acc: Option<Rect>, r| Some(acc.map_or(*r, |a| a.union(*r))))
+                    .unwrap_or(size);
+                detail::draw(f, area, &state, view, &config.thresholds);
+            }
         })?;
     }
 
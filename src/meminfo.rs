@@ -0,0 +1,194 @@
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::units;
+
+/// The parts of `/proc/meminfo` the Memory panel breaks RAM down into, in bytes.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MemInfo {
+    pub total: u64,
+    pub free: u64,
+    pub available: u64,
+    pub buffers: u64,
+    /// Page cache, including shmem
+    pub cached: u64,
+    /// tmpfs and shared memory. Counted in `cached` but can't be dropped like it
+    pub shmem: u64,
+    pub slab_reclaimable: u64,
+    pub slab_unreclaimable: u64,
+    pub dirty: u64,
+    pub writeback: u64,
+    /// Page cache mapped into some process
+    pub mapped: u64,
+    pub committed_as: u64,
+    pub commit_limit: u64,
+    pub hugepages_total: u64,
+    pub hugepages_free: u64,
+    pub hugepage_size: u64,
+}
+
+impl MemInfo {
+    /// Memory reserved for huge pages, used or not. It shows up in none of the other fields.
+    pub fn hugepages(&self) -> u64 {
+        self.hugepages_total * self.hugepage_size
+    }
+
+    /// What's left once free memory, the caches, the slab and huge pages are taken out:
+    /// process memory plus the kernel allocations meminfo doesn't itemize
+    pub fn used(&self) -> u64 {
+        self.total
+            .saturating_sub(self.free)
+            .saturating_sub(self.buffers)
+            .saturating_sub(self.cached)
+            .saturating_sub(self.slab_reclaimable)
+            .saturating_sub(self.slab_unreclaimable)
+            .saturating_sub(self.hugepages())
+    }
+
+    /// The bar segments, left to right: what can't be reclaimed first, then the caches, then
+    /// free memory. Together they add up to `total`.
+    fn segments(&self) -> [(&'static str, u64, Color); 8] {
+        [
+            ("Apps", self.used(), Color::Green),
+            ("Shmem", self.shmem, Color::Magenta),
+            ("Slab", self.slab_unreclaimable, Color::Red),
+            ("Huge", self.hugepages(), Color::Blue),
+            ("Buffers", self.buffers, Color::Cyan),
+            ("Cache", self.cached.saturating_sub(self.shmem), Color::Yellow),
+            ("Slab rec.", self.slab_reclaimable, Color::LightYellow),
+            ("Free", self.free, Color::DarkGray),
+        ]
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn read() -> Option<MemInfo> {
+    let text = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let mut info = MemInfo::default();
+    for line in text.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut value = value.split_whitespace();
+        let Some(number) = value.next().and_then(|v| v.parse::<u64>().ok()) else {
+            continue;
+        };
+        // HugePages_Total and friends are page counts, everything else is in kB
+        let bytes = if value.next() == Some("kB") { number * 1024 } else { number };
+        let field = match name {
+            "MemTotal" => &mut info.total,
+            "MemFree" => &mut info.free,
+            "MemAvailable" => &mut info.available,
+            "Buffers" => &mut info.buffers,
+            "Cached" => &mut info.cached,
+            "Shmem" => &mut info.shmem,
+            "SReclaimable" => &mut info.slab_reclaimable,
+            "SUnreclaim" => &mut info.slab_unreclaimable,
+            "Dirty" => &mut info.dirty,
+            "Writeback" => &mut info.writeback,
+            "Mapped" => &mut info.mapped,
+            "Committed_AS" => &mut info.committed_as,
+            "CommitLimit" => &mut info.commit_limit,
+            "HugePages_Total" => &mut info.hugepages_total,
+            "HugePages_Free" => &mut info.hugepages_free,
+            "Hugepagesize" => &mut info.hugepage_size,
+            _ => continue,
+        };
+        *field = bytes;
+    }
+    (info.total > 0).then_some(info)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read() -> Option<MemInfo> {
+    None
+}
+
+/// The stacked bar, a legend and the writeback/commit figures, wrapped to `width` columns.
+pub fn breakdown_lines(info: &MemInfo, width: u16) -> Vec<Line<'static>> {
+    let width = width.max(1) as u64;
+    let segments = info.segments();
+
+    // Each segment ends where its running total falls, so rounding never adds up past the width
+    let mut bar = Vec::new();
+    let mut sum = 0;
+    let mut drawn = 0;
+    for (_, bytes, color) in segments {
+        sum += bytes;
+        let end = (sum.min(info.total) * width + info.total / 2) / info.total.max(1);
+        let cells = end.saturating_sub(drawn);
+        drawn += cells;
+        if cells > 0 {
+            bar.push(Span::styled("█".repeat(cells as usize), Style::default().fg(color)));
+        }
+    }
+
+    let mut lines = vec![Line::from(bar)];
+    let legend = segments
+        .iter()
+        .filter(|(_, bytes, _)| *bytes > 0)
+        .map(|(label, bytes, color)| {
+            vec![
+                Span::styled("■ ", Style::default().fg(*color)),
+                Span::raw(format!("{} {}", label, units::bytes(*bytes))),
+            ]
+        })
+        .collect();
+    lines.extend(pack(legend, width));
+
+    // Past the limit, allocations fail when overcommit is strict (vm.overcommit_memory = 2)
+    let commit_percent = info.committed_as as f64 / info.commit_limit.max(1) as f64 * 100.0;
+    let mut details = vec![
+        vec![Span::styled(
+            format!(
+                "Committed {} of {} ({:.0}%)",
+                units::bytes(info.committed_as),
+                units::bytes(info.commit_limit),
+                commit_percent
+            ),
+            if commit_percent > 100.0 {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            },
+        )],
+        vec![Span::raw(format!("Dirty {}", units::bytes(info.dirty)))],
+        vec![Span::raw(format!("Writeback {}", units::bytes(info.writeback)))],
+        vec![Span::raw(format!("Mapped {}", units::bytes(info.mapped)))],
+    ];
+    if info.hugepages_total > 0 {
+        details.push(vec![Span::raw(format!(
+            "Huge pages {}/{} free",
+            info.hugepages_free, info.hugepages_total
+        ))]);
+    }
+    lines.extend(pack(details, width));
+    lines
+}
+
+/// Puts `items` on as few lines as fit in `width`, two spaces apart. An item is never split.
+fn pack(items: Vec<Vec<Span<'static>>>, width: u64) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut line: Vec<Span> = Vec::new();
+    let mut used = 0;
+    for item in items {
+        let len: u64 = item.iter().map(|s| s.width() as u64).sum();
+        if !line.is_empty() && used + 2 + len > width {
+            lines.push(Line::from(std::mem::take(&mut line)));
+            used = 0;
+        }
+        if !line.is_empty() {
+            line.push(Span::raw("  "));
+            used += 2;
+        }
+        used += len;
+        line.extend(item);
+    }
+    if !line.is_empty() {
+        lines.push(Line::from(line));
+    }
+    lines
+}
@@ -0,0 +1,193 @@
+use std::fmt::Write as _;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::{SharedState, TaskCounts};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+// Requests bigger than this are cut off, a scrape is a single short GET
+const MAX_REQUEST: usize = 8192;
+// A client that hasn't sent its request by then is dropped so it can't hold a socket open
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+// Back-off after a failed accept, e.g. EMFILE, which would otherwise fail again right away
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+/// Binds `addr` right away so a bad address is reported before the UI starts, then
+/// serves `/metrics` from a background thread running a small tokio runtime.
+pub fn spawn(addr: SocketAddr, top_n: usize, state: Arc<Mutex<SharedState>>) -> io::Result<()> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .enable_time()
+        .build()?;
+
+    thread::spawn(move || {
+        runtime.block_on(async move {
+            let Ok(listener) = TcpListener::from_std(listener) else { return };
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    tokio::time::sleep(ACCEPT_RETRY).await;
+                    continue;
+                };
+                let state = Arc::clone(&state);
+                tokio::spawn(async move {
+                    let _ = handle(stream, top_n, state).await;
+                });
+            }
+        });
+    });
+    Ok(())
+}
+
+async fn handle(mut stream: TcpStream, top_n: usize, state: Arc<Mutex<SharedState>>) -> io::Result<()> {
+    let request = tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream))
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.lines().next().unwrap_or("").split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("");
+    let path = path.split('?').next().unwrap_or(path);
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => {
+            let body = match state.lock() {
+                Ok(state) => render(&state, top_n),
+                Err(_) => "# EOF\n".to_string(),
+            };
+            ("200 OK", CONTENT_TYPE, body)
+        }
+        ("GET", _) => ("404 Not Found", "text/plain", "Not found, try /metrics\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "Only GET is supported\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn read_request(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    Ok(request)
+}
+
+/// Renders everything in `SharedState` in the OpenMetrics text format.
+pub fn render(state: &SharedState, top_n: usize) -> String {
+    let mut out = String::new();
+
+    gauge(&mut out, "taskmanager_cpu_usage_percent", "Total CPU usage across all cores.", state.total_cpu_usage as f64);
+    gauge(&mut out, "taskmanager_memory_total_bytes", "Total RAM.", state.total_memory as f64);
+    gauge(&mut out, "taskmanager_memory_used_bytes", "Used RAM.", state.used_memory as f64);
+    gauge(&mut out, "taskmanager_memory_available_bytes", "RAM available for new allocations.", state.available_memory as f64);
+    gauge(&mut out, "taskmanager_swap_total_bytes", "Total swap.", state.total_swap as f64);
+    gauge(&mut out, "taskmanager_swap_used_bytes", "Used swap.", state.used_swap as f64);
+    gauge(&mut out, "taskmanager_disk_read_bytes_per_second", "Disk read rate of the physical block devices, summed over processes where /proc/diskstats is unavailable.", state.disk_read_bps);
+    gauge(&mut out, "taskmanager_disk_write_bytes_per_second", "Disk write rate of the physical block devices, summed over processes where /proc/diskstats is unavailable.", state.disk_write_bps);
+    gauge(&mut out, "taskmanager_processes", "Number of processes and threads listed.", state.processes.len() as f64);
+    gauge(&mut out, "taskmanager_uptime_seconds", "Time since boot.", state.host.uptime as f64);
+    gauge(&mut out, "taskmanager_boot_time_seconds", "Boot time as a Unix timestamp.", state.host.boot_time as f64);
+
+    header(&mut out, "taskmanager_load_average", "gauge", "Load average over 1, 5 and 15 minutes.");
+    for (window, value) in ["1m", "5m", "15m"].iter().zip(state.host.load_average) {
+        sample(&mut out, "taskmanager_load_average", &[("window", window)], value);
+    }
+
+    let tasks = TaskCounts::from_processes(&state.processes);
+    header(&mut out, "taskmanager_tasks", "gauge", "Processes by state, threads not included.");
+    for (task_state, count) in [
+        ("running", tasks.running),
+        ("sleeping", tasks.sleeping),
+        ("stopped", tasks.stopped),
+        ("zombie", tasks.zombie),
+    ] {
+        sample(&mut out, "taskmanager_tasks", &[("state", task_state)], count as f64);
+    }
+
+    header(&mut out, "taskmanager_network_receive_bytes", "counter", "Bytes received per interface.");
+    for net in &state.network_data {
+        sample(&mut out, "taskmanager_network_receive_bytes_total", &[("interface", &net.name)], net.rx_total as f64);
+    }
+    header(&mut out, "taskmanager_network_transmit_bytes", "counter", "Bytes sent per interface.");
+    for net in &state.network_data {
+        sample(&mut out, "taskmanager_network_transmit_bytes_total", &[("interface", &net.name)], net.tx_total as f64);
+    }
+    header(&mut out, "taskmanager_network_receive_bytes_per_second", "gauge", "Receive rate per interface.");
+    for net in &state.network_data {
+        sample(&mut out, "taskmanager_network_receive_bytes_per_second", &[("interface", &net.name)], net.rx_bps);
+    }
+    header(&mut out, "taskmanager_network_transmit_bytes_per_second", "gauge", "Transmit rate per interface.");
+    for net in &state.network_data {
+        sample(&mut out, "taskmanager_network_transmit_bytes_per_second", &[("interface", &net.name)], net.tx_bps);
+    }
+
+    // Only the busiest processes, to keep label cardinality bounded
+    let mut by_cpu: Vec<_> = state.processes.iter().filter(|p| !p.is_thread).collect();
+    by_cpu.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage).then(a.pid.cmp(&b.pid)));
+    header(&mut out, "taskmanager_process_cpu_usage_percent", "gauge", "CPU usage of the top processes by CPU.");
+    for p in by_cpu.iter().take(top_n) {
+        let pid = p.pid.to_string();
+        sample(&mut out, "taskmanager_process_cpu_usage_percent", &[("pid", &pid), ("name", &p.name)], p.cpu_usage as f64);
+    }
+
+    let mut by_memory = by_cpu;
+    by_memory.sort_by(|a, b| b.memory.cmp(&a.memory).then(a.pid.cmp(&b.pid)));
+    header(&mut out, "taskmanager_process_memory_bytes", "gauge", "Resident memory of the top processes by memory.");
+    for p in by_memory.iter().take(top_n) {
+        let pid = p.pid.to_string();
+        sample(&mut out, "taskmanager_process_memory_bytes", &[("pid", &pid), ("name", &p.name)], p.memory as f64);
+    }
+
+    out.push_str("# EOF\n");
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    header(out, name, "gauge", help);
+    sample(out, name, &[], value);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+            .collect();
+        let _ = write!(out, "{{{}}}", labels.join(","));
+    }
+    let _ = writeln!(out, " {}", value);
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,478 @@
+use sysinfo::{Pid, Signal};
+
+// Signals that can be sent from the `kill` command and the process table
+const SIGNALS: [(Signal, &str); 9] = [
+    (Signal::Term, "SIGTERM"),
+    (Signal::Kill, "SIGKILL"),
+    (Signal::Hangup, "SIGHUP"),
+    (Signal::Stop, "SIGSTOP"),
+    (Signal::Continue, "SIGCONT"),
+    (Signal::Interrupt, "SIGINT"),
+    (Signal::Quit, "SIGQUIT"),
+    (Signal::User1, "SIGUSR1"),
+    (Signal::User2, "SIGUSR2"),
+];
+
+pub fn signal_name(signal: Signal) -> &'static str {
+    SIGNALS
+        .iter()
+        .find(|(s, _)| *s == signal)
+        .map_or("SIG?", |(_, name)| name)
+}
+
+/// Accepts `TERM`, `SIGTERM`, `term` or the signal number (`15`).
+pub fn parse_signal(s: &str) -> Option<Signal> {
+    let upper = s.trim().to_uppercase();
+    if let Ok(num) = upper.parse::<i32>() {
+        return SIGNALS
+            .iter()
+            .map(|(sig, _)| *sig)
+            .find(|sig| signal_number(*sig) == Some(num));
+    }
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS
+        .iter()
+        .find(|(_, n)| &n[3..] == name)
+        .map(|(sig, _)| *sig)
+}
+
+#[cfg(unix)]
+fn signal_number(signal: Signal) -> Option<i32> {
+    match signal {
+        Signal::Term => Some(libc::SIGTERM),
+        Signal::Kill => Some(libc::SIGKILL),
+        Signal::Hangup => Some(libc::SIGHUP),
+        Signal::Stop => Some(libc::SIGSTOP),
+        Signal::Continue => Some(libc::SIGCONT),
+        Signal::Interrupt => Some(libc::SIGINT),
+        Signal::Quit => Some(libc::SIGQUIT),
+        Signal::User1 => Some(libc::SIGUSR1),
+        Signal::User2 => Some(libc::SIGUSR2),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn signal_number(signal: Signal) -> Option<i32> {
+    match signal {
+        Signal::Term => Some(15),
+        Signal::Kill => Some(9),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+pub fn send_signal(pid: Pid, signal: Signal) -> std::io::Result<()> {
+    let sig = signal_number(signal)
+        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::Unsupported))?;
+    // SAFETY: kill(2) has no memory-safety requirements
+    let ret = unsafe { libc::kill(pid.as_u32() as libc::pid_t, sig) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send_signal(pid: Pid, signal: Signal) -> std::io::Result<()> {
+    let mut sys = sysinfo::System::new();
+    sys.refresh_process(pid);
+    let proc = sys
+        .process(pid)
+        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+    match proc.kill_with(signal) {
+        Some(true) => Ok(()),
+        Some(false) => Err(std::io::Error::from(std::io::ErrorKind::PermissionDenied)),
+        None => Err(std::io::Error::from(std::io::ErrorKind::Unsupported)),
+    }
+}
+
+/// `EPERM` and the like, for the errors the process controls usually run into.
+#[cfg(unix)]
+pub fn errno_name(err: &std::io::Error) -> Option<&'static str> {
+    match err.raw_os_error() {
+        Some(libc::EPERM) => Some("EPERM"),
+        Some(libc::ESRCH) => Some("ESRCH"),
+        Some(libc::EINVAL) => Some("EINVAL"),
+        Some(libc::EACCES) => Some("EACCES"),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn errno_name(_err: &std::io::Error) -> Option<&'static str> {
+    None
+}
+
+/// Formats an OS error as `EPERM (Operation not permitted)` where the errno is known.
+pub fn describe_os_error(err: &std::io::Error) -> String {
+    let msg = err.to_string();
+    let msg = msg.split(" (os error").next().unwrap_or(&msg).to_string();
+    match errno_name(err) {
+        Some(name) => format!("{} ({})", name, msg),
+        None => msg,
+    }
+}
+
+/// I/O scheduling class, as used by `ionice`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IoClass {
+    None,
+    Realtime,
+    BestEffort,
+    Idle,
+}
+
+impl IoClass {
+    /// Accepts the `ionice` names and numbers: `none`/0, `realtime`/`rt`/1,
+    /// `best-effort`/`be`/2, `idle`/3.
+    pub fn parse(s: &str) -> Option<IoClass> {
+        match s.trim().to_lowercase().as_str() {
+            "none" | "0" => Some(IoClass::None),
+            "realtime" | "rt" | "1" => Some(IoClass::Realtime),
+            "best-effort" | "besteffort" | "be" | "2" => Some(IoClass::BestEffort),
+            "idle" | "3" => Some(IoClass::Idle),
+            _ => None,
+        }
+    }
+
+    fn from_number(n: i32) -> Option<IoClass> {
+        match n {
+            0 => Some(IoClass::None),
+            1 => Some(IoClass::Realtime),
+            2 => Some(IoClass::BestEffort),
+            3 => Some(IoClass::Idle),
+            _ => None,
+        }
+    }
+
+    fn number(self) -> i32 {
+        match self {
+            IoClass::None => 0,
+            IoClass::Realtime => 1,
+            IoClass::BestEffort => 2,
+            IoClass::Idle => 3,
+        }
+    }
+}
+
+/// Short form shown in the table: `be/4`, `rt/0`, `idle` or `none`.
+pub fn format_io_priority(class: IoClass, level: u8) -> String {
+    match class {
+        IoClass::None => "none".to_string(),
+        IoClass::Realtime => format!("rt/{}", level),
+        IoClass::BestEffort => format!("be/{}", level),
+        IoClass::Idle => "idle".to_string(),
+    }
+}
+
+/// Nice value of one process (or thread), from `/proc/<pid>/stat`.
+#[cfg(target_os = "linux")]
+pub fn nice_of(pid: Pid) -> Option<i32> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name can contain spaces and parentheses, fields are counted after the last ')'.
+    // State is field 3 and nice field 19.
+    let fields = &stat[stat.rfind(')')? + 1..];
+    fields.split_whitespace().nth(16)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn nice_of(_pid: Pid) -> Option<i32> {
+    None
+}
+
+// ioprio_get/ioprio_set have no libc wrappers
+#[cfg(target_os = "linux")]
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+#[cfg(target_os = "linux")]
+const IOPRIO_CLASS_SHIFT: i32 = 13;
+
+#[cfg(target_os = "linux")]
+pub fn io_priority_of(pid: Pid) -> Option<(IoClass, u8)> {
+    // SAFETY: ioprio_get only reads its integer arguments
+    let ret = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, pid.as_u32() as libc::c_int) };
+    if ret < 0 {
+        return None;
+    }
+    let ret = ret as i32;
+    let class = IoClass::from_number(ret >> IOPRIO_CLASS_SHIFT)?;
+    Some((class, (ret & ((1 << IOPRIO_CLASS_SHIFT) - 1)) as u8))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn io_priority_of(_pid: Pid) -> Option<(IoClass, u8)> {
+    None
+}
+
+/// Parses a PID for the commands that change a process. PID 0 would make the syscalls act
+/// on the caller and `Pid::from` truncates anything past `i32::MAX`, so both are refused.
+pub fn parse_pid(s: &str) -> Option<Pid> {
+    s.parse::<i32>().ok().filter(|pid| *pid > 0).map(|pid| Pid::from(pid as usize))
+}
+
+/// Thread IDs of a process, empty if it doesn't exist. Nice values, I/O priorities and CPU
+/// affinity are per thread on Linux, so changes have to be applied to each of them.
+#[cfg(target_os = "linux")]
+pub fn thread_ids(pid: Pid) -> Vec<Pid> {
+    std::fs::read_dir(format!("/proc/{}/task", pid))
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<usize>().ok())
+                .map(Pid::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn thread_ids(pid: Pid) -> Vec<Pid> {
+    vec![pid]
+}
+
+/// How a change applied to the threads of a process went.
+pub struct ThreadChanges {
+    pub changed: usize,
+    /// Threads that still existed when their turn came
+    pub total: usize,
+    /// The first error of the threads that refused the change, and how many did
+    pub failed: Option<(std::io::Error, usize)>,
+}
+
+/// Runs `apply` on every thread of `pid`. Returns the first error if no thread could be
+/// changed, ESRCH if the process is gone. Threads that exit halfway through are skipped.
+fn for_each_thread(pid: Pid, apply: impl Fn(Pid) -> std::io::Result<()>) -> std::io::Result<ThreadChanges> {
+    let mut changes = ThreadChanges {
+        changed: 0,
+        total: 0,
+        failed: None,
+    };
+    let tids = thread_ids(pid);
+    if tids.is_empty() {
+        return Err(std::io::Error::from_raw_os_error(ESRCH));
+    }
+    for tid in tids {
+        match apply(tid) {
+            Ok(()) => changes.changed += 1,
+            Err(e) if e.raw_os_error() == Some(ESRCH) && tid != pid => continue,
+            Err(e) => changes.failed.get_or_insert((e, 0)).1 += 1,
+        }
+        changes.total += 1;
+    }
+    match changes.failed {
+        Some((e, _)) if changes.changed == 0 => Err(e),
+        _ => Ok(changes),
+    }
+}
+
+#[cfg(unix)]
+const ESRCH: i32 = libc::ESRCH;
+#[cfg(not(unix))]
+const ESRCH: i32 = 3;
+
+/// setpriority(2) on every thread of `pid`.
+#[cfg(unix)]
+pub fn set_nice(pid: Pid, nice: i32) -> std::io::Result<ThreadChanges> {
+    for_each_thread(pid, |tid| {
+        // SAFETY: setpriority has no memory-safety requirements
+        let ret = unsafe { libc::setpriority(libc::PRIO_PROCESS, tid.as_u32() as libc::id_t, nice) };
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    })
+}
+
+#[cfg(not(unix))]
+pub fn set_nice(_pid: Pid, _nice: i32) -> std::io::Result<ThreadChanges> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+/// ioprio_set(2) on every thread of `pid`. `level` (0 = highest, 7 = lowest) is ignored
+/// for the idle and none classes.
+#[cfg(target_os = "linux")]
+pub fn set_io_priority(pid: Pid, class: IoClass, level: u8) -> std::io::Result<ThreadChanges> {
+    let level = if matches!(class, IoClass::Realtime | IoClass::BestEffort) { level as i32 } else { 0 };
+    let ioprio = (class.number() << IOPRIO_CLASS_SHIFT) | level;
+    for_each_thread(pid, |tid| {
+        // SAFETY: ioprio_set only reads its integer arguments
+        let ret = unsafe {
+            libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, tid.as_u32() as libc::c_int, ioprio)
+        };
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_io_priority(_pid: Pid, _class: IoClass, _level: u8) -> std::io::Result<ThreadChanges> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+// CPUs an affinity mask can hold. Checked while parsing so a range like `0-4000000000`
+// fails at once instead of being expanded
+#[cfg(target_os = "linux")]
+const MAX_CPUS: usize = libc::CPU_SETSIZE as usize;
+#[cfg(not(target_os = "linux"))]
+const MAX_CPUS: usize = 1024;
+
+/// Parses a `taskset -c` style CPU list (`0-3,6`) or a hex mask (`0x4f`).
+pub fn parse_cpu_list(s: &str) -> Result<Vec<usize>, String> {
+    let s = s.trim();
+    let mut cpus = Vec::new();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        if hex.is_empty() {
+            return Err("empty CPU mask".to_string());
+        }
+        // Least significant digit last, like taskset
+        for (i, digit) in hex.chars().rev().enumerate() {
+            let bits = digit.to_digit(16).ok_or_else(|| format!("invalid CPU mask '{}'", s))?;
+            cpus.extend((0..4).filter(|b| bits & (1 << b) != 0).map(|b| i * 4 + b));
+        }
+    } else {
+        for part in s.split(',').filter(|p| !p.trim().is_empty()) {
+            let parse = |n: &str| n.trim().parse::<usize>().map_err(|_| format!("invalid CPU list '{}'", s));
+            match part.split_once('-') {
+                Some((first, last)) => {
+                    let (first, last) = (parse(first)?, parse(last)?);
+                    if first > last {
+                        return Err(format!("invalid CPU range '{}'", part));
+                    }
+                    if last >= MAX_CPUS {
+                        return Err(format!("CPU {} is past the {} CPUs an affinity mask can hold", last, MAX_CPUS));
+                    }
+                    cpus.extend(first..=last);
+                }
+                None => cpus.push(parse(part)?),
+            }
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    match cpus.last() {
+        None => Err("the CPU list selects no CPUs".to_string()),
+        // Single CPUs and mask bits, ranges were checked before expanding them
+        Some(&last) if last >= MAX_CPUS => Err(format!("CPU {} is past the {} CPUs an affinity mask can hold", last, MAX_CPUS)),
+        Some(_) => Ok(cpus),
+    }
+}
+
+/// `0-3,6`: consecutive CPUs collapsed into ranges.
+pub fn format_cpu_list(cpus: &[usize]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < cpus.len() {
+        let start = cpus[i];
+        while i + 1 < cpus.len() && cpus[i + 1] == cpus[i] + 1 {
+            i += 1;
+        }
+        parts.push(if cpus[i] == start { start.to_string() } else { format!("{}-{}", start, cpus[i]) });
+        i += 1;
+    }
+    parts.join(",")
+}
+
+/// CPUs a process (or thread) may run on, from sched_getaffinity(2).
+#[cfg(target_os = "linux")]
+pub fn cpu_affinity(pid: Pid) -> std::io::Result<Vec<usize>> {
+    // SAFETY: cpu_set_t is plain data and the kernel writes at most size_of::<cpu_set_t>() bytes
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(pid.as_u32() as libc::pid_t, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok((0..libc::CPU_SETSIZE as usize).filter(|cpu| libc::CPU_ISSET(*cpu, &set)).collect())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn cpu_affinity(_pid: Pid) -> std::io::Result<Vec<usize>> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+/// sched_setaffinity(2) on every thread of `pid`.
+#[cfg(target_os = "linux")]
+pub fn set_cpu_affinity(pid: Pid, cpus: &[usize]) -> std::io::Result<ThreadChanges> {
+    if let Some(cpu) = cpus.iter().find(|cpu| **cpu >= libc::CPU_SETSIZE as usize) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("CPU {} is past the {} CPUs an affinity mask can hold", cpu, libc::CPU_SETSIZE),
+        ));
+    }
+    // SAFETY: cpu_set_t is plain data and every index was checked against CPU_SETSIZE
+    let set = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for cpu in cpus {
+            libc::CPU_SET(*cpu, &mut set);
+        }
+        set
+    };
+    for_each_thread(pid, |tid| {
+        // SAFETY: `set` outlives the call and the size matches its type
+        let ret = unsafe {
+            libc::sched_setaffinity(tid.as_u32() as libc::pid_t, std::mem::size_of::<libc::cpu_set_t>(), &set)
+        };
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_cpu_affinity(_pid: Pid, _cpus: &[usize]) -> std::io::Result<ThreadChanges> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cpu_lists() {
+        assert_eq!(parse_cpu_list("0-3,6"), Ok(vec![0, 1, 2, 3, 6]));
+        assert_eq!(parse_cpu_list(" 2 , 2,1-2,"), Ok(vec![1, 2]));
+        assert_eq!(parse_cpu_list("5-5"), Ok(vec![5]));
+        assert!(parse_cpu_list("3-1").is_err());
+        assert!(parse_cpu_list("1-").is_err());
+        assert!(parse_cpu_list("a").is_err());
+        assert!(parse_cpu_list("").is_err());
+        assert!(parse_cpu_list(",").is_err());
+        assert!(parse_cpu_list(&format!("0-{}", MAX_CPUS)).is_err());
+        assert!(parse_cpu_list(&MAX_CPUS.to_string()).is_err());
+        assert_eq!(parse_cpu_list(&(MAX_CPUS - 1).to_string()), Ok(vec![MAX_CPUS - 1]));
+    }
+
+    #[test]
+    fn parses_cpu_masks() {
+        assert_eq!(parse_cpu_list("0x1"), Ok(vec![0]));
+        assert_eq!(parse_cpu_list("0X4f"), Ok(vec![0, 1, 2, 3, 6]));
+        assert_eq!(parse_cpu_list("0x100"), Ok(vec![8]));
+        assert_eq!(parse_cpu_list(&format!("0x{}1", "0".repeat(MAX_CPUS))), Ok(vec![0]));
+        assert!(parse_cpu_list("0x").is_err());
+        assert!(parse_cpu_list("0x0").is_err());
+        assert!(parse_cpu_list("0xg").is_err());
+        assert!(parse_cpu_list(&format!("0x1{}", "0".repeat(MAX_CPUS / 4))).is_err());
+    }
+
+    #[test]
+    fn formats_what_it_parses() {
+        for list in ["0", "0-3,6", "1,3,5", "0-1,4-7,9"] {
+            assert_eq!(format_cpu_list(&parse_cpu_list(list).unwrap()), list);
+        }
+    }
+
+    #[test]
+    fn parses_pids() {
+        assert_eq!(parse_pid("1"), Some(Pid::from(1)));
+        assert_eq!(parse_pid(&i32::MAX.to_string()), Some(Pid::from(i32::MAX as usize)));
+        assert_eq!(parse_pid("0"), None);
+        assert_eq!(parse_pid("-1"), None);
+        assert_eq!(parse_pid("4294967296"), None);
+        assert_eq!(parse_pid("2147483648"), None);
+    }
+}
@@ -0,0 +1,429 @@
+use std::io;
+
+use sysinfo::Pid;
+
+// Per-process data sysinfo doesn't expose, read straight from /proc/<pid>. The readers
+// return ErrorKind::Unsupported (or nothing) on other platforms.
+
+/// One entry of `/proc/<pid>/fd`.
+pub struct OpenFile {
+    pub fd: u32,
+    /// Where the descriptor points: a path, `socket:[inode]`, `pipe:[inode]`, `anon_inode:...`
+    pub target: String,
+}
+
+impl OpenFile {
+    /// Inode of a socket descriptor, to look it up in `/proc/<pid>/net/*`.
+    pub fn socket_inode(&self) -> Option<u64> {
+        self.target.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+    }
+
+    /// What kind of object the descriptor refers to, like lsof's TYPE column.
+    pub fn kind(&self) -> FileKind {
+        let target = self.target.as_str();
+        if target.starts_with("socket:") {
+            FileKind::Socket
+        } else if target.starts_with("pipe:") {
+            FileKind::Pipe
+        } else if target.starts_with("anon_inode:") {
+            FileKind::AnonInode
+        } else if target.ends_with(" (deleted)") {
+            // Unlinked but still open, e.g. a rotated log that keeps filling the disk
+            FileKind::Deleted
+        } else if target.starts_with("/dev/") {
+            FileKind::Device
+        } else if target.starts_with('/') {
+            FileKind::File
+        } else {
+            FileKind::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FileKind {
+    File,
+    Deleted,
+    Device,
+    Socket,
+    Pipe,
+    AnonInode,
+    Other,
+}
+
+impl FileKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            FileKind::File => "file",
+            FileKind::Deleted => "deleted",
+            FileKind::Device => "device",
+            FileKind::Socket => "socket",
+            FileKind::Pipe => "pipe",
+            FileKind::AnonInode => "anon",
+            FileKind::Other => "other",
+        }
+    }
+}
+
+/// Soft and hard `RLIMIT_NOFILE`, None for unlimited.
+#[derive(Debug, Clone, Copy)]
+pub struct FdLimits {
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+}
+
+/// One mapping of `/proc/<pid>/smaps`.
+pub struct MemoryMap {
+    pub start: u64,
+    pub end: u64,
+    /// `r-xp` and friends
+    pub perms: String,
+    pub offset: u64,
+    /// File path, `[heap]`, `[stack]`, ... or empty for anonymous memory
+    pub path: String,
+    pub usage: MemoryUsage,
+}
+
+/// Resident memory split the way smaps does, in bytes. Summed over all mappings this is
+/// `/proc/<pid>/smaps_rollup`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryUsage {
+    pub rss: u64,
+    /// Proportional set size, every shared page divided by the number of processes mapping it
+    pub pss: u64,
+    pub shared_clean: u64,
+    pub shared_dirty: u64,
+    pub private_clean: u64,
+    pub private_dirty: u64,
+    pub swap: u64,
+}
+
+impl MemoryUsage {
+    /// Unique set size, what killing the process would give back
+    pub fn uss(&self) -> u64 {
+        self.private_clean + self.private_dirty
+    }
+
+    /// Takes one `Name:   123 kB` line, ignoring the fields it doesn't track.
+    fn add_field(&mut self, line: &str) {
+        let Some((name, value)) = line.split_once(':') else {
+            return;
+        };
+        let Some(kb) = value.trim().strip_suffix(" kB").and_then(|v| v.trim().parse::<u64>().ok()) else {
+            return;
+        };
+        let field = match name {
+            "Rss" => &mut self.rss,
+            "Pss" => &mut self.pss,
+            "Shared_Clean" => &mut self.shared_clean,
+            "Shared_Dirty" => &mut self.shared_dirty,
+            "Private_Clean" => &mut self.private_clean,
+            "Private_Dirty" => &mut self.private_dirty,
+            "Swap" => &mut self.swap,
+            _ => return,
+        };
+        *field += kb * 1024;
+    }
+}
+
+impl MemoryMap {
+    pub fn size(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+/// A socket the process holds a descriptor for.
+pub struct Socket {
+    pub fd: u32,
+    /// `tcp`, `tcp6`, `udp`, `udp6` or `unix`
+    pub protocol: &'static str,
+    pub local: String,
+    pub remote: String,
+    pub state: String,
+}
+
+#[cfg(target_os = "linux")]
+pub fn open_files(pid: Pid) -> io::Result<Vec<OpenFile>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(format!("/proc/{}/fd", pid))? {
+        let Ok(entry) = entry else { continue };
+        let Some(fd) = entry.file_name().to_str().and_then(|n| n.parse().ok()) else {
+            continue;
+        };
+        match std::fs::read_link(entry.path()) {
+            Ok(target) => files.push(OpenFile {
+                fd,
+                target: target.to_string_lossy().into_owned(),
+            }),
+            // The descriptor can be closed between listing and reading the link
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            // Listing works without ptrace access, resolving the links doesn't
+            Err(e) => return Err(e),
+        }
+    }
+    files.sort_by_key(|f| f.fd);
+    Ok(files)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn open_files(_pid: Pid) -> io::Result<Vec<OpenFile>> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Number of open descriptors, without resolving them. Still a directory listing per
+/// process, so the collector only counts while the FDs column is shown or sorted on.
+#[cfg(target_os = "linux")]
+pub fn fd_count(pid: Pid) -> Option<usize> {
+    Some(std::fs::read_dir(format!("/proc/{}/fd", pid)).ok()?.count())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn fd_count(_pid: Pid) -> Option<usize> {
+    None
+}
+
+/// `RLIMIT_NOFILE` of any process, from the `Max open files` line of `/proc/<pid>/limits`
+/// (prlimit(2) would need the same permissions as changing it).
+#[cfg(target_os = "linux")]
+pub fn fd_limits(pid: Pid) -> io::Result<FdLimits> {
+    let text = std::fs::read_to_string(format!("/proc/{}/limits", pid))?;
+    let line = text
+        .lines()
+        .find_map(|l| l.strip_prefix("Max open files"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no 'Max open files' limit"))?;
+    let mut values = line.split_whitespace().map(|v| v.parse::<u64>().ok());
+    Ok(FdLimits {
+        soft: values.next().flatten(),
+        hard: values.next().flatten(),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn fd_limits(_pid: Pid) -> io::Result<FdLimits> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Every mapping with its resident memory. Reading smaps walks the page tables of the
+/// whole process, so this is for one PID at a time.
+#[cfg(target_os = "linux")]
+pub fn memory_maps(pid: Pid) -> io::Result<Vec<MemoryMap>> {
+    let text = std::fs::read_to_string(format!("/proc/{}/smaps", pid))?;
+    let mut maps: Vec<MemoryMap> = Vec::new();
+    for line in text.lines() {
+        // Mapping lines start with the address range, the fields under them with `Name:`
+        let is_field = line.split_whitespace().next().is_some_and(|first| first.ends_with(':'));
+        if is_field {
+            if let Some(map) = maps.last_mut() {
+                map.usage.add_field(line);
+            }
+        } else if let Some(map) = parse_map_line(line) {
+            maps.push(map);
+        }
+    }
+    Ok(maps)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn memory_maps(_pid: Pid) -> io::Result<Vec<MemoryMap>> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// `7f12a000-7f12c000 r-xp 00001000 08:01 1234   /usr/lib/libc.so.6`
+#[cfg(target_os = "linux")]
+fn parse_map_line(line: &str) -> Option<MemoryMap> {
+    let mut fields = line.splitn(6, ' ');
+    let (start, end) = fields.next()?.split_once('-')?;
+    let perms = fields.next()?.to_string();
+    let offset = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let _device = fields.next()?;
+    let _inode = fields.next()?;
+    Some(MemoryMap {
+        start: u64::from_str_radix(start, 16).ok()?,
+        end: u64::from_str_radix(end, 16).ok()?,
+        perms,
+        offset,
+        path: fields.next().unwrap_or("").trim().to_string(),
+        usage: MemoryUsage::default(),
+    })
+}
+
+/// Totals of `memory_maps` without the per-mapping lines. Kernels before 4.14 have no
+/// smaps_rollup, those get smaps summed up.
+#[cfg(target_os = "linux")]
+pub fn memory_usage(pid: Pid) -> io::Result<MemoryUsage> {
+    let text = match std::fs::read_to_string(format!("/proc/{}/smaps_rollup", pid)) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound && std::path::Path::new(&format!("/proc/{}", pid)).exists() => {
+            std::fs::read_to_string(format!("/proc/{}/smaps", pid))?
+        }
+        text => text?,
+    };
+    let mut usage = MemoryUsage::default();
+    for line in text.lines() {
+        usage.add_field(line);
+    }
+    Ok(usage)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn memory_usage(_pid: Pid) -> io::Result<MemoryUsage> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Sockets among `files`, resolved through the socket tables of the process's network
+/// namespace.
+#[cfg(target_os = "linux")]
+pub fn sockets(pid: Pid, files: &[OpenFile]) -> Vec<Socket> {
+    use std::collections::HashMap;
+
+    let inodes: HashMap<u64, u32> = files
+        .iter()
+        .filter_map(|f| Some((f.socket_inode()?, f.fd)))
+        .collect();
+    if inodes.is_empty() {
+        return Vec::new();
+    }
+
+    let mut sockets = Vec::new();
+    for protocol in ["tcp", "tcp6", "udp", "udp6"] {
+        let Ok(table) = std::fs::read_to_string(format!("/proc/{}/net/{}", pid, protocol)) else {
+            continue;
+        };
+        // sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode
+        for line in table.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let Some(fd) = fields.get(9).and_then(|i| i.parse().ok()).and_then(|i: u64| inodes.get(&i)) else {
+                continue;
+            };
+            let (Some(local), Some(remote), Some(state)) = (
+                fields.get(1).and_then(|a| parse_address(a)),
+                fields.get(2).and_then(|a| parse_address(a)),
+                fields.get(3),
+            ) else {
+                continue;
+            };
+            sockets.push(Socket {
+                fd: *fd,
+                protocol,
+                local,
+                remote,
+                state: inet_state(protocol, state).to_string(),
+            });
+        }
+    }
+
+    if let Ok(table) = std::fs::read_to_string(format!("/proc/{}/net/unix", pid)) {
+        // Num RefCount Protocol Flags Type St Inode Path
+        for line in table.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let Some(fd) = fields.get(6).and_then(|i| i.parse().ok()).and_then(|i: u64| inodes.get(&i)) else {
+                continue;
+            };
+            let listening = fields.get(3).and_then(|f| u32::from_str_radix(f, 16).ok()).is_some_and(|f| f & 0x10000 != 0);
+            let kind = match fields.get(4).copied() {
+                Some("0001") => "stream",
+                Some("0002") => "dgram",
+                Some("0005") => "seqpacket",
+                _ => "?",
+            };
+            let state = match (listening, fields.get(5).copied()) {
+                (true, _) => "LISTEN",
+                (_, Some("03")) => "CONNECTED",
+                (_, Some("02")) => "CONNECTING",
+                (_, Some("04")) => "DISCONNECTING",
+                _ => "UNCONNECTED",
+            };
+            sockets.push(Socket {
+                fd: *fd,
+                protocol: "unix",
+                local: fields.get(7).map_or(String::new(), |p| p.to_string()),
+                remote: kind.to_string(),
+                state: state.to_string(),
+            });
+        }
+    }
+
+    sockets.sort_by_key(|s| s.fd);
+    sockets
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn sockets(_pid: Pid, _files: &[OpenFile]) -> Vec<Socket> {
+    Vec::new()
+}
+
+/// `0100007F:0050` → `127.0.0.1:80`. The kernel prints each 32-bit word of the address
+/// as a host-order integer.
+#[cfg(target_os = "linux")]
+fn parse_address(hex: &str) -> Option<String> {
+    let (addr, port) = hex.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    if addr.len() % 8 != 0 {
+        return None;
+    }
+    let words = (0..addr.len() / 8)
+        .map(|i| u32::from_str_radix(addr.get(i * 8..i * 8 + 8)?, 16).ok())
+        .collect::<Option<Vec<u32>>>()?;
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_ne_bytes()).collect();
+    let ip = match bytes.len() {
+        4 => std::net::IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?),
+        16 => {
+            let v6 = std::net::Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?);
+            // Dual-stack sockets show IPv4 peers as ::ffff:a.b.c.d
+            v6.to_ipv4_mapped().map_or(std::net::IpAddr::V6(v6), std::net::IpAddr::V4)
+        }
+        _ => return None,
+    };
+    Some(match ip {
+        std::net::IpAddr::V6(v6) => format!("[{}]:{}", v6, port),
+        ip => format!("{}:{}", ip, port),
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn inet_state(protocol: &str, state: &str) -> &'static str {
+    let udp = protocol.starts_with("udp");
+    match state {
+        "01" => "ESTABLISHED",
+        "02" => "SYN_SENT",
+        "03" => "SYN_RECV",
+        "04" => "FIN_WAIT1",
+        "05" => "FIN_WAIT2",
+        "06" => "TIME_WAIT",
+        "07" if udp => "UNCONN",
+        "07" => "CLOSE",
+        "08" => "CLOSE_WAIT",
+        "09" => "LAST_ACK",
+        "0A" => "LISTEN",
+        "0B" => "CLOSING",
+        _ => "?",
+    }
+}
+
+// The kernel prints the words in host order, these are the lines a little-endian machine shows
+#[cfg(all(test, target_os = "linux", target_endian = "little"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ipv4_addresses() {
+        assert_eq!(parse_address("0100007F:0050").as_deref(), Some("127.0.0.1:80"));
+        assert_eq!(parse_address("00000000:0016").as_deref(), Some("0.0.0.0:22"));
+        assert_eq!(parse_address("0101A8C0:FFFF").as_deref(), Some("192.168.1.1:65535"));
+    }
+
+    #[test]
+    fn parses_ipv6_addresses() {
+        assert_eq!(parse_address("00000000000000000000000000000000:0016").as_deref(), Some("[::]:22"));
+        assert_eq!(parse_address("00000000000000000000000001000000:1F90").as_deref(), Some("[::1]:8080"));
+        assert_eq!(parse_address("B80D0120000000000000000001000000:0050").as_deref(), Some("[2001:db8::1]:80"));
+        // Dual-stack sockets list IPv4 peers as ::ffff:a.b.c.d
+        assert_eq!(parse_address("0000000000000000FFFF00000100007F:01BB").as_deref(), Some("127.0.0.1:443"));
+    }
+
+    #[test]
+    fn rejects_malformed_addresses() {
+        for hex in ["0100007F", "0100007F:", "0100007F:10000", "0100007G:0050", "0100007F0:0050", "01007F:0050", ":0050"] {
+            assert_eq!(parse_address(hex), None, "{}", hex);
+        }
+    }
+}
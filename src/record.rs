@@ -0,0 +1,574 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::collector::{self, ProcessSample};
+use crate::history::{self, MetricHistory};
+use crate::{NetworkInfo, SharedState};
+
+// A recording has one JSON object per line. The first line after opening and then every
+// KEYFRAME_EVERY-th carries the whole sample under `key`, the lines in between only what
+// changed since the line before under `delta`, with the processes keyed by PID so an idle
+// process costs nothing. Recordings with a full `sample` on every line load as well.
+
+const KEYFRAME_EVERY: usize = 60;
+// Lists the members a delta removes from an object. No struct field or PID is called that
+const REMOVED: &str = "-";
+
+/// One sample of a recording: a process-monitor sample plus the network rows at that time.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecordedSample {
+    /// Unix time in milliseconds
+    pub t: u64,
+    pub sample: ProcessSample,
+    pub network: Vec<NetworkInfo>,
+}
+
+/// Appends samples to a recording.
+pub struct Recorder {
+    out: BufWriter<File>,
+    /// The sample last written, which the next delta is taken against
+    last: Option<Value>,
+    since_keyframe: usize,
+}
+
+impl Recorder {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        // A recording cut short mid-line gets its own line, the keyframe written next doesn't
+        let len = file.metadata()?.len();
+        if len > 0 {
+            let mut last = [0];
+            let mut reader = File::open(path)?;
+            reader.seek(SeekFrom::Start(len - 1))?;
+            reader.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                writeln!(file)?;
+            }
+        }
+        Ok(Recorder {
+            out: BufWriter::new(file),
+            last: None,
+            since_keyframe: 0,
+        })
+    }
+
+    pub fn append(&mut self, sample: &ProcessSample, network: &[NetworkInfo]) -> io::Result<()> {
+        let t = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let frame = frame_value(sample, network)?;
+        let mut line = Map::new();
+        line.insert("t".to_string(), Value::from(t));
+        match &self.last {
+            Some(last) if self.since_keyframe < KEYFRAME_EVERY => {
+                let delta = diff(last, &frame).unwrap_or_else(|| Value::Object(Map::new()));
+                line.insert("delta".to_string(), delta);
+                self.since_keyframe += 1;
+            }
+            _ => {
+                line.insert("key".to_string(), frame.clone());
+                self.since_keyframe = 1;
+            }
+        }
+        self.last = Some(frame);
+        serde_json::to_writer(&mut self.out, &line)?;
+        writeln!(self.out)?;
+        // Flush every sample so a crash or Ctrl-C loses at most one line
+        self.out.flush()
+    }
+}
+
+/// A sample as JSON, with the process list turned into an object keyed by PID.
+fn frame_value(sample: &ProcessSample, network: &[NetworkInfo]) -> serde_json::Result<Value> {
+    #[derive(Serialize)]
+    struct Frame<'a> {
+        sample: &'a ProcessSample,
+        network: &'a [NetworkInfo],
+    }
+    let mut frame = serde_json::to_value(Frame { sample, network })?;
+    if let Some(processes) = frame.pointer_mut("/sample/processes") {
+        if let Value::Array(list) = processes {
+            let by_pid = std::mem::take(list)
+                .into_iter()
+                .filter_map(|p| Some((p.get("pid")?.to_string(), p)))
+                .collect();
+            *processes = Value::Object(by_pid);
+        }
+    }
+    Ok(frame)
+}
+
+/// The `RecordedSample` a frame built by `frame_value` (or a line of the old format) holds.
+fn sample_from(t: u64, frame: &Value) -> serde_json::Result<RecordedSample> {
+    let mut frame = frame.clone();
+    if let Some(processes) = frame.pointer_mut("/sample/processes") {
+        if let Value::Object(by_pid) = processes {
+            let list = std::mem::take(by_pid).into_iter().map(|(_, p)| p).collect();
+            *processes = Value::Array(list);
+        }
+    }
+    if let Some(frame) = frame.as_object_mut() {
+        frame.insert("t".to_string(), Value::from(t));
+    }
+    serde_json::from_value(frame)
+}
+
+/// What changed from `old` to `new`, None if nothing did. Objects are compared member by
+/// member, anything else is replaced whole.
+fn diff(old: &Value, new: &Value) -> Option<Value> {
+    if old == new {
+        return None;
+    }
+    let (Value::Object(old), Value::Object(new)) = (old, new) else {
+        return Some(new.clone());
+    };
+    let mut changes: Map<String, Value> = new
+        .iter()
+        .filter_map(|(name, value)| match old.get(name) {
+            Some(before) => diff(before, value).map(|d| (name.clone(), d)),
+            None => Some((name.clone(), value.clone())),
+        })
+        .collect();
+    let removed: Vec<Value> = old
+        .keys()
+        .filter(|name| !new.contains_key(*name))
+        .map(|name| Value::from(name.as_str()))
+        .collect();
+    if !removed.is_empty() {
+        changes.insert(REMOVED.to_string(), Value::Array(removed));
+    }
+    Some(Value::Object(changes))
+}
+
+/// Applies a delta made by `diff`.
+fn patch(target: &mut Value, delta: Value) {
+    match (target, delta) {
+        (Value::Object(target), Value::Object(mut delta)) => {
+            if let Some(Value::Array(removed)) = delta.remove(REMOVED) {
+                for name in removed.iter().filter_map(Value::as_str) {
+                    target.remove(name);
+                }
+            }
+            for (name, value) in delta {
+                match target.get_mut(&name) {
+                    Some(member) => patch(member, value),
+                    None => {
+                        target.insert(name, value);
+                    }
+                }
+            }
+        }
+        (target, delta) => *target = delta,
+    }
+}
+
+/// Where one usable sample starts in the file.
+struct FrameIndex {
+    offset: u64,
+    t: u64,
+    keyframe: bool,
+}
+
+/// A recording on disk. Only the position of each sample is kept in memory, the samples
+/// themselves are read as the replay gets to them.
+pub struct Recording {
+    file: File,
+    frames: Vec<FrameIndex>,
+    skipped: usize,
+    first_skipped: Option<String>,
+}
+
+impl Recording {
+    /// Lines that couldn't be used, summed up for a warning.
+    pub fn warning(&self) -> Option<String> {
+        let first = self.first_skipped.as_ref()?;
+        Some(match self.skipped {
+            1 => format!("skipped an unreadable line of the recording ({})", first),
+            n => format!("skipped {} unreadable lines of the recording, the first {}", n, first),
+        })
+    }
+}
+
+/// The header of a line, enough to index it without building the sample.
+#[derive(Deserialize)]
+struct LineHeader {
+    t: u64,
+    key: Option<IgnoredAny>,
+    delta: Option<IgnoredAny>,
+    sample: Option<IgnoredAny>,
+}
+
+/// Indexes a recording. Lines that can't be read, usually the last one of a recording cut
+/// short, are skipped along with the deltas that would have applied on top of them.
+pub fn load(path: &Path) -> io::Result<Recording> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut recording = Recording {
+        // Opened again for the player, a clone would share the read position
+        file: File::open(path)?,
+        frames: Vec::new(),
+        skipped: 0,
+        first_skipped: None,
+    };
+    let mut line = Vec::new();
+    let mut offset = 0;
+    let mut number = 0;
+    let mut have_base = false;
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        number += 1;
+        let start = offset;
+        offset += read as u64;
+        let text = line.trim_ascii();
+        if text.is_empty() {
+            continue;
+        }
+        let problem = match serde_json::from_slice::<LineHeader>(text) {
+            Ok(header) if header.key.is_some() || header.sample.is_some() => {
+                have_base = true;
+                recording.frames.push(FrameIndex { offset: start, t: header.t, keyframe: true });
+                continue;
+            }
+            Ok(header) if header.delta.is_some() && have_base => {
+                recording.frames.push(FrameIndex { offset: start, t: header.t, keyframe: false });
+                continue;
+            }
+            Ok(header) if header.delta.is_some() => "no keyframe before it".to_string(),
+            Ok(_) => "not a sample".to_string(),
+            Err(e) => e.to_string(),
+        };
+        have_base = false;
+        recording.skipped += 1;
+        recording.first_skipped.get_or_insert_with(|| format!("line {}: {}", number, problem));
+    }
+    if recording.frames.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            recording.first_skipped.unwrap_or_else(|| "recording is empty".to_string()),
+        ));
+    }
+    Ok(recording)
+}
+
+/// Reads the samples of a `Recording`, going back to the nearest keyframe to jump.
+struct Player {
+    frames: Vec<FrameIndex>,
+    reader: BufReader<File>,
+    /// Where `reader` is, to skip the seek when reading on
+    offset: u64,
+    frame: Value,
+    /// The sample `frame` holds
+    position: Option<usize>,
+}
+
+impl Player {
+    fn new(recording: Recording) -> Self {
+        Player {
+            frames: recording.frames,
+            reader: BufReader::new(recording.file),
+            offset: 0,
+            frame: Value::Null,
+            position: None,
+        }
+    }
+
+    fn sample(&mut self, position: usize) -> io::Result<RecordedSample> {
+        let start = match self.position.take() {
+            Some(p) if p + 1 == position => position,
+            _ => (0..=position).rev().find(|&i| self.frames[i].keyframe).unwrap_or(0),
+        };
+        for i in start..=position {
+            self.read(i)?;
+        }
+        self.position = Some(position);
+        Ok(sample_from(self.frames[position].t, &self.frame)?)
+    }
+
+    fn read(&mut self, i: usize) -> io::Result<()> {
+        let offset = self.frames[i].offset;
+        if self.offset != offset {
+            self.reader.seek(SeekFrom::Start(offset))?;
+        }
+        let mut line = Vec::new();
+        self.offset = offset + self.reader.read_until(b'\n', &mut line)? as u64;
+        let mut line: Value = serde_json::from_slice(&line)?;
+        if let Some(key) = line.get_mut("key") {
+            self.frame = key.take();
+        } else if let Some(delta) = line.get_mut("delta") {
+            patch(&mut self.frame, delta.take());
+        } else {
+            self.frame = line;
+        }
+        Ok(())
+    }
+}
+
+/// Playback position and controls, shared between the UI and the replay thread.
+pub struct ReplayState {
+    pub position: usize,
+    pub len: usize,
+    pub speed: f64,
+    /// Frames to move by on the next tick, set by the step keys
+    pub pending_step: i64,
+    /// Timestamp of the frame currently shown
+    pub t: u64,
+}
+
+impl ReplayState {
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(64.0);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(0.25);
+    }
+}
+
+/// Typical time between samples: the median gap, so pauses during recording don't count.
+fn sample_interval(frames: &[FrameIndex]) -> Duration {
+    let mut gaps: Vec<u64> = frames.windows(2).map(|w| w[1].t.saturating_sub(w[0].t)).collect();
+    gaps.sort_unstable();
+    match gaps.get(gaps.len() / 2) {
+        Some(&gap) if gap > 0 => Duration::from_millis(gap),
+        _ => Duration::from_secs(1),
+    }
+}
+
+/// Feeds a recording into `state` in place of the live collectors.
+pub fn spawn_replay(recording: Recording, state: Arc<Mutex<SharedState>>) {
+    let mut player = Player::new(recording);
+    let len = player.frames.len();
+    // The histories span the same time as live, at the interval the recording was made at
+    let interval = sample_interval(&player.frames);
+    let window = history::capacity(interval);
+    if let Ok(mut s) = state.lock() {
+        s.history = MetricHistory::new(interval);
+        s.replay = Some(ReplayState {
+            position: 0,
+            len,
+            speed: 1.0,
+            pending_step: 0,
+            t: player.frames[0].t,
+        });
+        show(&mut player, 0, &mut s);
+    }
+
+    thread::spawn(move || {
+        let mut position = 0;
+        let mut since_advance = Duration::ZERO;
+        let tick = Duration::from_millis(50);
+        loop {
+            thread::sleep(tick);
+            since_advance += tick;
+
+            let Ok(mut s) = state.lock() else { return };
+            let Some(replay) = s.replay.as_mut() else { return };
+            let speed = replay.speed;
+            let step = std::mem::take(&mut replay.pending_step);
+
+            let mut target = position as i64 + step;
+            if step == 0 && !s.paused {
+                // Wait as long as the recording did between these two samples
+                let next = (position + 1).min(len - 1);
+                let gap = player.frames[next].t.saturating_sub(player.frames[position].t).max(1);
+                let wait = Duration::from_millis((gap as f64 / speed) as u64);
+                if since_advance < wait {
+                    continue;
+                }
+                target += 1;
+            }
+            let target = target.clamp(0, len as i64 - 1) as usize;
+            if target == position {
+                continue;
+            }
+            since_advance = Duration::ZERO;
+
+            if target == position + 1 {
+                show(&mut player, target, &mut s);
+            } else {
+                // Jumping (stepping back): rebuild the history window up to the target
+                s.history = MetricHistory::new(interval);
+                s.process_history.clear();
+                for i in (target + 1).saturating_sub(window)..=target {
+                    show(&mut player, i, &mut s);
+                }
+            }
+            position = target;
+        }
+    });
+}
+
+/// Shows one sample. One that can't be read (the file changed underneath) keeps the one
+/// before on screen.
+fn show(player: &mut Player, position: usize, state: &mut SharedState) {
+    if let Ok(frame) = player.sample(position) {
+        frame.sample.apply(state);
+        collector::apply_network(frame.network, state);
+    }
+    if let Some(replay) = state.replay.as_mut() {
+        replay.position = position;
+        replay.t = player.frames[position].t;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HostInfo, ProcessInfo};
+    use sysinfo::Pid;
+
+    fn process(pid: usize, name: &str, cpu_usage: f32, memory: u64) -> ProcessInfo {
+        ProcessInfo {
+            name: name.to_string(),
+            pid: Pid::from(pid),
+            cpu_usage,
+            memory,
+            status: "Sleep".to_string(),
+            run_time: 60,
+            parent: Some(Pid::from(1)),
+            is_thread: false,
+            cmd: format!("/usr/bin/{}", name),
+            user: "root".to_string(),
+            threads: 1,
+            disk_read_bps: 0.0,
+            disk_write_bps: 0.0,
+            nice: None,
+            io_priority: None,
+            start_time: 0,
+            fd_count: None,
+            pss: None,
+            uss: None,
+        }
+    }
+
+    /// Tick `tick` of a made-up machine: an idle process, a busy one, one that is only
+    /// there on some ticks and a worker whose PID changes every seventh tick.
+    fn sample(tick: usize) -> (ProcessSample, Vec<NetworkInfo>) {
+        let mut busy = process(2, "busy", tick as f32 * 0.5, 1000 + tick as u64);
+        busy.nice = (tick % 4 == 0).then_some(-5);
+        let mut processes = vec![process(1, "init", 0.0, 4096), busy, process(1000 + tick / 7, "worker", 1.0, 2048)];
+        if tick % 3 != 0 {
+            processes.push(process(50, "flaky", 0.25, 512));
+        }
+        let mut network = vec![NetworkInfo {
+            name: "eth0".to_string(),
+            rx_total: tick as u64 * 100,
+            tx_total: 7,
+            rx_bps: 100.0,
+            tx_bps: 0.0,
+            master: None,
+        }];
+        if tick % 5 < 2 {
+            network.push(NetworkInfo {
+                name: "veth1".to_string(),
+                rx_total: 1,
+                tx_total: 1,
+                rx_bps: 0.0,
+                tx_bps: 0.0,
+                master: Some("docker0".to_string()),
+            });
+        }
+        let sample = ProcessSample {
+            processes,
+            host: HostInfo::default(),
+            cpu_model: "test".to_string(),
+            total_cpu_usage: (tick % 10) as f32,
+            cores: Vec::new(),
+            total_memory: 8192,
+            used_memory: 4096 + tick as u64,
+            available_memory: 4096 - tick as u64,
+            total_swap: 0,
+            used_swap: 0,
+            disk_read_bps: 0.0,
+            disk_write_bps: tick as f64,
+            meminfo: None,
+            disks: Vec::new(),
+        };
+        (sample, network)
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("taskmanager-{}-{}.ndjson", name, std::process::id()))
+    }
+
+    fn record(path: &Path, ticks: std::ops::Range<usize>) {
+        let mut recorder = Recorder::open(path).unwrap();
+        for tick in ticks {
+            let (sample, network) = sample(tick);
+            recorder.append(&sample, &network).unwrap();
+        }
+    }
+
+    fn assert_replays(player: &mut Player, position: usize, tick: usize) {
+        let replayed = player.sample(position).unwrap();
+        let (sample, network) = sample(tick);
+        assert_eq!(
+            frame_value(&replayed.sample, &replayed.network).unwrap(),
+            frame_value(&sample, &network).unwrap(),
+            "sample {} differs from tick {}",
+            position,
+            tick
+        );
+    }
+
+    #[test]
+    fn round_trips_across_keyframes() {
+        let path = temp_path("round-trip");
+        let _ = std::fs::remove_file(&path);
+        let ticks = 2 * KEYFRAME_EVERY + 10;
+        record(&path, 0..ticks);
+
+        let recording = load(&path).unwrap();
+        assert!(recording.warning().is_none());
+        let keyframes: Vec<usize> = (0..ticks).filter(|&i| recording.frames[i].keyframe).collect();
+        assert_eq!(keyframes, [0, KEYFRAME_EVERY, 2 * KEYFRAME_EVERY]);
+
+        let mut player = Player::new(recording);
+        for i in 0..ticks {
+            assert_replays(&mut player, i, i);
+        }
+        // Stepping back across a keyframe replays from the one before it
+        for i in (KEYFRAME_EVERY - 3..=KEYFRAME_EVERY + 2).rev() {
+            assert_replays(&mut player, i, i);
+        }
+        assert_replays(&mut player, 5, 5);
+        assert_replays(&mut player, ticks - 1, ticks - 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn skips_a_line_cut_short_and_records_on_after_it() {
+        let path = temp_path("truncated");
+        let _ = std::fs::remove_file(&path);
+        record(&path, 0..5);
+        let text = std::fs::read_to_string(&path).unwrap();
+        let last_line = text.trim_end().rfind('\n').unwrap() + 1;
+        std::fs::write(&path, &text[..last_line + (text.len() - last_line) / 2]).unwrap();
+
+        let recording = load(&path).unwrap();
+        assert_eq!(recording.frames.len(), 4);
+        assert!(recording.warning().unwrap().contains("line 5"));
+
+        // Recording again starts on a line of its own with a keyframe
+        record(&path, 10..12);
+        let recording = load(&path).unwrap();
+        assert_eq!(recording.frames.len(), 6);
+        assert!(recording.frames[4].keyframe);
+        let mut player = Player::new(recording);
+        for (position, tick) in [0, 1, 2, 3, 10, 11].into_iter().enumerate() {
+            assert_replays(&mut player, position, tick);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::ProcessInfo;
+
+/// Process search typed after `/`. Plain text is a case-insensitive substring match,
+/// a `re:` prefix switches to a (case-insensitive) regex.
+pub struct Search {
+    pub pattern: String,
+    // Plain text is searched as an escaped regex too, so match offsets always fall on
+    // char boundaries of the original text, which lowercasing doesn't guarantee
+    regex: Option<Regex>,
+    pub error: Option<String>,
+}
+
+impl Search {
+    pub fn new(pattern: &str) -> Self {
+        let mut search = Search {
+            pattern: pattern.to_string(),
+            regex: None,
+            error: None,
+        };
+        let expr = match pattern.strip_prefix("re:") {
+            Some(expr) => expr.to_string(),
+            None => regex::escape(pattern),
+        };
+        match RegexBuilder::new(&expr).case_insensitive(true).build() {
+            Ok(re) => search.regex = Some(re),
+            Err(e) => search.error = Some(e.to_string().lines().last().unwrap_or("invalid regex").to_string()),
+        }
+        search
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_empty()
+    }
+
+    /// Matches against name, command line, user and PID.
+    pub fn matches(&self, p: &ProcessInfo) -> bool {
+        if self.is_empty() || self.error.is_some() {
+            return false;
+        }
+        let pid = p.pid.to_string();
+        [p.name.as_str(), p.cmd.as_str(), p.user.as_str(), pid.as_str()]
+            .iter()
+            .any(|field| self.is_match(field))
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        self.regex.as_ref().is_some_and(|re| re.is_match(text))
+    }
+
+    /// Byte range of the first match in `text`, used to highlight the name column.
+    pub fn find(&self, text: &str) -> Option<(usize, usize)> {
+        let m = self.regex.as_ref()?.find(text)?;
+        (!m.is_empty()).then(|| (m.start(), m.end()))
+    }
+}
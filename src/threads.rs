@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+use sysinfo::Pid;
+
+use crate::config::Thresholds;
+
+/// One thread of the process shown in the thread view.
+pub struct ThreadInfo {
+    pub tid: Pid,
+    pub name: String,
+    /// Kernel state letter: R, S, D, T, t, Z, X or I
+    pub state: char,
+    pub cpu_usage: f32,
+    /// CPU the thread last ran on
+    pub last_cpu: Option<usize>,
+}
+
+/// Raw counters from `/proc/<pid>/task/<tid>/stat`.
+struct ThreadStat {
+    tid: Pid,
+    name: String,
+    state: char,
+    /// utime + stime in clock ticks
+    ticks: u64,
+    last_cpu: Option<usize>,
+}
+
+/// Threads of one process for the detail view, CPU % computed against the previous `refresh`.
+pub struct ThreadView {
+    pub pid: Pid,
+    pub name: String,
+    pub threads: Vec<ThreadInfo>,
+    pub table_state: TableState,
+    last_ticks: HashMap<Pid, u64>,
+    last_tick: Instant,
+    exited: bool,
+}
+
+impl ThreadView {
+    pub fn new(pid: Pid, name: String) -> Self {
+        let mut view = ThreadView {
+            pid,
+            name,
+            threads: Vec::new(),
+            table_state: TableState::default().with_selected(Some(0)),
+            last_ticks: HashMap::new(),
+            last_tick: Instant::now(),
+            exited: false,
+        };
+        view.refresh();
+        view
+    }
+
+    pub fn refresh(&mut self) {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_tick).as_secs_f64().max(1e-9);
+        let stats = read_threads(self.pid);
+        self.exited = stats.is_empty();
+        if self.exited {
+            // Keep the last sample on screen, marked as exited
+            return;
+        }
+
+        let selected_tid = self.table_state.selected().and_then(|i| self.threads.get(i)).map(|t| t.tid);
+        let tick_rate = clock_ticks_per_second();
+        self.threads = stats
+            .iter()
+            .map(|t| {
+                // A thread seen for the first time has no baseline yet
+                let cpu_usage = self
+                    .last_ticks
+                    .get(&t.tid)
+                    .map_or(0.0, |prev| t.ticks.saturating_sub(*prev) as f64 / tick_rate / dt * 100.0);
+                ThreadInfo {
+                    tid: t.tid,
+                    name: t.name.clone(),
+                    state: t.state,
+                    cpu_usage: cpu_usage as f32,
+                    last_cpu: t.last_cpu,
+                }
+            })
+            .collect();
+        // Busiest first, the spinning worker is what people open this for
+        self.threads.sort_by(|a, b| {
+            b.cpu_usage
+                .partial_cmp(&a.cpu_usage)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.tid.cmp(&b.tid))
+        });
+        // Stay on the same thread across re-sorts
+        if let Some(i) = selected_tid.and_then(|tid| self.threads.iter().position(|t| t.tid == tid)) {
+            self.table_state.select(Some(i));
+        }
+        self.last_ticks = stats.into_iter().map(|t| (t.tid, t.ticks)).collect();
+        self.last_tick = now;
+    }
+
+    /// Moves the selection by `delta` rows, clamped to the thread list.
+    pub fn select(&mut self, delta: isize) {
+        let last = self.threads.len().saturating_sub(1);
+        let current = self.table_state.selected().unwrap_or(0);
+        self.table_state.select(Some(current.saturating_add_signed(delta).min(last)));
+    }
+}
+
+pub fn state_name(state: char) -> &'static str {
+    match state {
+        'R' => "running",
+        'S' => "sleeping",
+        'D' => "disk sleep",
+        'T' => "stopped",
+        't' => "tracing",
+        'Z' => "zombie",
+        'X' => "dead",
+        'I' => "idle",
+        _ => "unknown",
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_threads(pid: Pid) -> Vec<ThreadStat> {
+    let Ok(dir) = std::fs::read_dir(format!("/proc/{}/task", pid)) else {
+        return Vec::new();
+    };
+    dir.filter_map(|entry| {
+        let entry = entry.ok()?;
+        let tid = Pid::from(entry.file_name().to_str()?.parse::<usize>().ok()?);
+        let stat = std::fs::read_to_string(entry.path().join("stat")).ok()?;
+        parse_stat(tid, &stat)
+    })
+    .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_threads(_pid: Pid) -> Vec<ThreadStat> {
+    Vec::new()
+}
+
+/// Fields of `stat` as numbered in proc(5): name is 2, state 3, utime 14, stime 15,
+/// processor 39.
+#[cfg(target_os = "linux")]
+fn parse_stat(tid: Pid, stat: &str) -> Option<ThreadStat> {
+    // The name can contain spaces and parentheses, so split around the outer pair
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let name = stat.get(open + 1..close)?.to_string();
+    let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+    // fields[0] is field 3
+    let field = |n: usize| fields.get(n - 3).copied();
+    let ticks = field(14)?.parse::<u64>().ok()? + field(15)?.parse::<u64>().ok()?;
+    Some(ThreadStat {
+        tid,
+        name,
+        state: field(3)?.chars().next()?,
+        ticks,
+        last_cpu: field(39).and_then(|f| f.parse().ok()),
+    })
+}
+
+#[cfg(unix)]
+fn clock_ticks_per_second() -> f64 {
+    // SAFETY: sysconf has no memory-safety requirements
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 {
+        ticks as f64
+    } else {
+        100.0
+    }
+}
+
+#[cfg(not(unix))]
+fn clock_ticks_per_second() -> f64 {
+    100.0
+}
+
+/// Thread table for the threads tab of the detail view.
+pub fn draw(f: &mut Frame, area: Rect, view: &mut ThreadView, thresholds: &Thresholds) {
+    if view.threads.is_empty() {
+        let message = if cfg!(target_os = "linux") {
+            "No threads found"
+        } else {
+            "The thread view needs /proc (Linux only)"
+        };
+        f.render_widget(Paragraph::new(message).style(Style::default().fg(Color::DarkGray)), area);
+        return;
+    }
+
+    let rows: Vec<Row> = view
+        .threads
+        .iter()
+        .map(|t| {
+            let style = if t.cpu_usage > thresholds.cpu_crit {
+                Style::default().fg(Color::Red)
+            } else if t.cpu_usage > thresholds.cpu_warn {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            Row::new(vec![
+                Cell::from(t.tid.to_string()),
+                Cell::from(t.name.clone()),
+                Cell::from(format!("{:.2}%", t.cpu_usage)),
+                Cell::from(format!("{} ({})", t.state, state_name(t.state))),
+                Cell::from(t.last_cpu.map_or("-".to_string(), |cpu| cpu.to_string())),
+            ])
+            .style(style)
+        })
+        .collect();
+
+    let header = format!(
+        "TID [{}/{}]{}",
+        view.table_state.selected().map_or(0, |i| i + 1),
+        view.threads.len(),
+        if view.exited { " exited" } else { "" }
+    );
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(14),
+            Constraint::Min(16),
+            Constraint::Length(9),
+            Constraint::Length(16),
+            Constraint::Length(8),
+        ],
+    )
+    .header(
+        Row::new(vec![header, "Name".to_string(), "CPU % ▼".to_string(), "State".to_string(), "Last CPU".to_string()])
+            .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+    )
+    .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+    .highlight_symbol("> ");
+    f.render_stateful_widget(table, area, &mut view.table_state);
+}
@@ -0,0 +1,127 @@
+use std::collections::{HashMap, HashSet};
+
+use sysinfo::Pid;
+
+use crate::ProcessInfo;
+
+/// One line of the process table, either in the flat list or in tree mode.
+pub struct TreeRow {
+    pub info: ProcessInfo,
+    pub depth: usize,
+    pub has_children: bool,
+    pub collapsed: bool,
+    /// Number of processes hidden under a collapsed row
+    pub hidden: usize,
+}
+
+impl TreeRow {
+    pub fn flat(info: ProcessInfo) -> Self {
+        TreeRow {
+            info,
+            depth: 0,
+            has_children: false,
+            collapsed: false,
+            hidden: 0,
+        }
+    }
+
+    /// Indentation and expand/collapse marker shown before the name in tree mode.
+    pub fn name_prefix(&self) -> String {
+        let marker = if !self.has_children {
+            "  "
+        } else if self.collapsed {
+            "▸ "
+        } else {
+            "▾ "
+        };
+        format!("{}{}", "  ".repeat(self.depth), marker)
+    }
+
+    /// Count of hidden processes shown after the name of a collapsed row.
+    pub fn name_suffix(&self) -> String {
+        if self.collapsed && self.hidden > 0 {
+            format!(" (+{})", self.hidden)
+        } else {
+            String::new()
+        }
+    }
+}
+
+/// Builds the tree rows from an already sorted process list, so siblings keep the
+/// current sort order. Collapsed rows show CPU and memory summed over their subtree.
+pub fn build_tree(procs: &[ProcessInfo], collapsed: &HashSet<Pid>) -> Vec<TreeRow> {
+    let known: HashSet<Pid> = procs.iter().map(|p| p.pid).collect();
+    let mut children: HashMap<Pid, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (i, p) in procs.iter().enumerate() {
+        match p.parent {
+            Some(parent) if parent != p.pid && known.contains(&parent) => {
+                children.entry(parent).or_default().push(i)
+            }
+            _ => roots.push(i),
+        }
+    }
+
+    let mut rows = Vec::with_capacity(procs.len());
+    let mut visited = HashSet::new();
+    for root in roots {
+        push_subtree(procs, &children, collapsed, root, 0, &mut visited, &mut rows);
+    }
+    rows
+}
+
+fn push_subtree(
+    procs: &[ProcessInfo],
+    children: &HashMap<Pid, Vec<usize>>,
+    collapsed: &HashSet<Pid>,
+    index: usize,
+    depth: usize,
+    visited: &mut HashSet<Pid>,
+    rows: &mut Vec<TreeRow>,
+) {
+    let info = &procs[index];
+    if !visited.insert(info.pid) {
+        return;
+    }
+    let kids = children.get(&info.pid).map(Vec::as_slice).unwrap_or(&[]);
+    let is_collapsed = !kids.is_empty() && collapsed.contains(&info.pid);
+
+    if is_collapsed {
+        let mut row = TreeRow {
+            info: info.clone(),
+            depth,
+            has_children: true,
+            collapsed: true,
+            hidden: 0,
+        };
+        let mut stack: Vec<usize> = kids.to_vec();
+        while let Some(i) = stack.pop() {
+            let p = &procs[i];
+            if !visited.insert(p.pid) {
+                continue;
+            }
+            row.hidden += 1;
+            // Threads share their process's memory and CPU time, don't count them twice
+            if !p.is_thread {
+                row.info.cpu_usage += p.cpu_usage;
+                row.info.memory += p.memory;
+            }
+            if let Some(grandkids) = children.get(&p.pid) {
+                stack.extend(grandkids);
+            }
+        }
+        rows.push(row);
+        return;
+    }
+
+    rows.push(TreeRow {
+        info: info.clone(),
+        depth,
+        has_children: !kids.is_empty(),
+        collapsed: false,
+        hidden: 0,
+    });
+    for &kid in kids {
+        push_subtree(procs, children, collapsed, kid, depth + 1, visited, rows);
+    }
+}
@@ -0,0 +1,155 @@
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+// Every size and rate on screen and in `--batch` text goes through here, so a config
+// change reaches all panels at once. CSV, JSON and the metrics always carry plain bytes.
+
+/// How sizes are scaled.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum UnitSystem {
+    /// Powers of 1024: KiB, MiB, GiB
+    Iec,
+    /// Powers of 1000: kB, MB, GB
+    Si,
+    /// Plain numbers of bytes (or bits) without a unit, for scripts
+    Raw,
+}
+
+impl FromStr for UnitSystem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "iec" | "binary" => Ok(UnitSystem::Iec),
+            "si" | "decimal" => Ok(UnitSystem::Si),
+            "raw" => Ok(UnitSystem::Raw),
+            _ => Err(format!("unknown unit system '{}', expected iec, si or raw", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for UnitSystem {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// What network rates are counted in.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum RateUnit {
+    Bytes,
+    /// Like link speeds and ISPs, 1 Gbit/s = 125 MB/s
+    Bits,
+}
+
+impl FromStr for RateUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "bytes" | "b" => Ok(RateUnit::Bytes),
+            "bits" | "bit" => Ok(RateUnit::Bits),
+            _ => Err(format!("unknown rate unit '{}', expected bytes or bits", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for RateUnit {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// The `[units]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Units {
+    pub system: UnitSystem,
+    /// Decimals of scaled values, `1.5 GiB` at 1
+    pub precision: usize,
+    /// Unit of the network rates, disk rates are always bytes
+    pub network: RateUnit,
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units {
+            system: UnitSystem::Iec,
+            precision: 1,
+            network: RateUnit::Bytes,
+        }
+    }
+}
+
+// More decimals than this only shows float noise
+pub const MAX_PRECISION: usize = 6;
+
+static UNITS: OnceLock<Units> = OnceLock::new();
+
+/// Sets the units for the rest of the run. Called once, right after the config is loaded.
+pub fn init(units: Units) {
+    let _ = UNITS.set(units);
+}
+
+fn current() -> Units {
+    UNITS.get().copied().unwrap_or_default()
+}
+
+/// A size, e.g. `1.5 GiB`.
+pub fn bytes(b: u64) -> String {
+    current().scaled(b as f64, "B")
+}
+
+/// A disk or per-process rate, e.g. `12.0 MiB/s`.
+pub fn rate(bytes_per_sec: f64) -> String {
+    current().rate(bytes_per_sec, RateUnit::Bytes)
+}
+
+/// A network rate, in bits per second if the config asks for it.
+pub fn net_rate(bytes_per_sec: f64) -> String {
+    let units = current();
+    units.rate(bytes_per_sec, units.network)
+}
+
+impl Units {
+    fn rate(&self, bytes_per_sec: f64, unit: RateUnit) -> String {
+        // Rates come out of divisions by elapsed time, a zero interval gives NaN or inf
+        let bytes_per_sec = if bytes_per_sec.is_finite() { bytes_per_sec.max(0.0) } else { 0.0 };
+        let scaled = match unit {
+            RateUnit::Bytes => self.scaled(bytes_per_sec, "B"),
+            RateUnit::Bits => self.scaled(bytes_per_sec * 8.0, "bit"),
+        };
+        match self.system {
+            UnitSystem::Raw => scaled,
+            _ => format!("{}/s", scaled),
+        }
+    }
+
+    fn scaled(&self, value: f64, unit: &str) -> String {
+        let (base, prefixes): (f64, [&str; 6]) = match self.system {
+            UnitSystem::Iec => (1024.0, ["", "Ki", "Mi", "Gi", "Ti", "Pi"]),
+            UnitSystem::Si => (1000.0, ["", "k", "M", "G", "T", "P"]),
+            UnitSystem::Raw => return format!("{:.0}", value),
+        };
+        let mut value = value;
+        let mut prefix = 0;
+        while value >= base && prefix < prefixes.len() - 1 {
+            value /= base;
+            prefix += 1;
+        }
+        if prefix == 0 {
+            // There are no fractions of a byte
+            format!("{:.0} {}", value, unit)
+        } else {
+            format!("{:.*} {}{}", self.precision, value, prefixes[prefix], unit)
+        }
+    }
+}